@@ -1,7 +1,7 @@
-use std::f64::consts::E;
-
 use serde::{Deserialize, Serialize};
 
+use crate::dual::Dual;
+use crate::float::Float;
 use crate::value::Value;
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
@@ -13,52 +13,62 @@ pub enum Activation {
 }
 
 impl Activation {
-    pub fn apply(&self, input: &[Value]) -> Vec<Value> {
+    pub fn apply<F: Float>(&self, input: &[Value<F>]) -> Vec<Value<F>> {
         match self {
-            Activation::Linear => input.iter().map(|x| x * 1.0).collect(),
-            Activation::Sigmoid => input.iter().map(|x| sigmoid(x)).collect(),
-            Activation::ReLU => input
-                .iter()
-                .map(|x| if x.value() > 0.0 { x * 1.0 } else { 0.0.into() })
-                .collect(),
+            Activation::Linear => input.to_vec(),
+            Activation::Sigmoid => input.iter().map(Value::sigmoid).collect(),
+            Activation::ReLU => input.iter().map(relu).collect(),
             Activation::Softmax => softmax(input),
         }
     }
 
-    pub fn apply_to_value(&self, x: &Value) -> Value {
+    /// Forward-mode counterpart of `apply`.
+    pub fn apply_dual(&self, input: &[Dual]) -> Vec<Dual> {
+        match self {
+            Activation::Linear => input.to_vec(),
+            Activation::Sigmoid => input.iter().map(|x| x.sigmoid()).collect(),
+            Activation::ReLU => input.iter().map(|x| x.max(Dual::constant(0.0))).collect(),
+            Activation::Softmax => softmax_dual(input),
+        }
+    }
+
+    pub fn apply_to_value<F: Float>(&self, x: &Value<F>) -> Value<F> {
         match self {
-            Activation::Linear => x * 1.0,
-            Activation::Sigmoid => sigmoid(x),
-            Activation::ReLU => {
-                if x.value() > 0.0 {
-                    x * 1.0
-                } else {
-                    0.0.into()
-                }
-            }
-            Activation::Softmax => sigmoid(x),
+            Activation::Linear => *x,
+            Activation::Sigmoid => x.sigmoid(),
+            Activation::ReLU => relu(x),
+            Activation::Softmax => x.sigmoid(),
         }
     }
 }
 
-fn sigmoid(x: &Value) -> Value {
-    1.0 / &(1.0 + &(E ^ &(-x)))
+fn relu<F: Float>(x: &Value<F>) -> Value<F> {
+    x.max(&Value::constant_leaf(F::ZERO))
 }
 
-fn softmax(input: &[Value]) -> Vec<Value> {
-    let mut max_val = Value::new(f64::NEG_INFINITY);
-    for x in input {
-        if x.value() > max_val.value() {
-            max_val = x.clone();
-        }
-    }
+fn softmax<F: Float>(input: &[Value<F>]) -> Vec<Value<F>> {
+    let Some((first, rest)) = input.split_first() else {
+        return Vec::new();
+    };
+    // Picked inside the graph, so a replayed `Plan` shifts by the current
+    // maximum. The shift doesn't change the result, so it is detached.
+    let max_val = rest.iter().fold(*first, |max, x| max.max(x)).detach();
 
-    let exps: Vec<Value> = input.iter().map(|x| E ^ &(x - &max_val)).collect();
+    let exps: Vec<Value<F>> = input.iter().map(|x| (x - &max_val).exp()).collect();
 
-    let mut exp_sum = Value::new(0.0);
-    for exp in &exps {
-        exp_sum = &exp_sum + exp;
-    }
+    let exp_sum = Value::sum(&exps);
 
     exps.iter().map(|exp| exp / &exp_sum).collect()
 }
+
+fn softmax_dual(input: &[Dual]) -> Vec<Dual> {
+    let max_val = input
+        .iter()
+        .map(|x| x.value)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let exps: Vec<Dual> = input.iter().map(|&x| (x - max_val).exp()).collect();
+    let exp_sum = exps.iter().fold(Dual::constant(0.0), |acc, &exp| acc + exp);
+
+    exps.iter().map(|&exp| exp / exp_sum).collect()
+}
@@ -0,0 +1,108 @@
+use std::fmt::Write;
+
+use crate::float::Float;
+use crate::tape::Tape;
+use crate::value::Operation;
+
+// Reports for `tape::DetectAnomaly`. They are only built once something
+// went wrong, so they are free to walk the tape.
+
+/// Longest path shown, longer ones keep the end next to the anomaly.
+const MAX_PATH: usize = 12;
+
+pub(crate) fn forward_report<F: Float>(tape: &Tape<F>, node: usize) -> String {
+    let mut report = format!(
+        "Anomaly detected: {} produced {}\n",
+        tape.op_name(node),
+        tape.nodes[node].value
+    );
+    writeln!(report, "  at {}", describe(tape, node)).unwrap();
+
+    // Back to a leaf, always through the most recently computed operand that
+    // is not a literal.
+    let mut path = vec![node];
+    while path.len() <= MAX_PATH {
+        let parents = tape.parents(*path.last().unwrap());
+        let parent = parents
+            .iter()
+            .filter(|&&parent| !matches!(tape.nodes[parent].op, Operation::Constant))
+            .max()
+            .or(parents.iter().max());
+        match parent {
+            Some(&parent) => path.push(parent),
+            None => break,
+        }
+    }
+    path.reverse();
+    write!(report, "  path: {}", format_path(tape, &path)).unwrap();
+    report
+}
+
+/// `node`, whose gradient is `node_grad`, passed the non-finite `grad` on to
+/// `parent`. `reachable` marks the nodes the backward pass went through.
+pub(crate) fn backward_report<F: Float>(
+    tape: &Tape<F>,
+    node: usize,
+    node_grad: F,
+    parent: usize,
+    grad: F,
+    reachable: &[bool],
+) -> String {
+    let mut report = format!(
+        "Anomaly detected: backward of {} passed {} to #{}\n",
+        tape.op_name(node),
+        grad,
+        parent
+    );
+    writeln!(
+        report,
+        "  at {}, with gradient {}",
+        describe(tape, node),
+        node_grad
+    )
+    .unwrap();
+
+    // Up to a root, through the first reachable node using the current one.
+    let mut path = vec![parent, node];
+    while path.len() <= MAX_PATH {
+        let current = *path.last().unwrap();
+        let child = (current + 1..reachable.len())
+            .find(|&child| reachable[child] && tape.parents(child).contains(&current));
+        match child {
+            Some(child) => path.push(child),
+            None => break,
+        }
+    }
+    write!(report, "  path: {}", format_path(tape, &path)).unwrap();
+    report
+}
+
+fn describe<F: Float>(tape: &Tape<F>, node: usize) -> String {
+    let operands: Vec<String> = tape
+        .parents(node)
+        .iter()
+        .map(|&parent| format!("#{} = {}", parent, tape.nodes[parent].value))
+        .collect();
+    if operands.is_empty() {
+        format!(
+            "#{} {} = {}",
+            node,
+            tape.op_name(node),
+            tape.nodes[node].value
+        )
+    } else {
+        format!("#{} {}({})", node, tape.op_name(node), operands.join(", "))
+    }
+}
+
+fn format_path<F: Float>(tape: &Tape<F>, path: &[usize]) -> String {
+    let steps: Vec<String> = path
+        .iter()
+        .map(|&node| format!("#{} {}", node, tape.op_name(node)))
+        .collect();
+    if path.len() > MAX_PATH {
+        format!("... -> {}", steps[path.len() - MAX_PATH..].join(" -> "))
+    } else {
+        steps.join(" -> ")
+    }
+}
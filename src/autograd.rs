@@ -0,0 +1,319 @@
+use crate::float::Float;
+use crate::matrix::Matrix;
+use crate::tape::{self, with_tape};
+use crate::value::{
+    Operation, Value, backward_from, check_gradients, local_gradients, no_checkpoints,
+};
+
+/// Backpropagates from several outputs at once, `seeds[i]` being the
+/// upstream gradient of `outputs[i]`. Gradients are accumulated into the
+/// `grad` fields like `Value::backward` does.
+pub fn backward<F: Float>(outputs: &[Value<F>], seeds: &[F]) {
+    assert_eq!(
+        outputs.len(),
+        seeds.len(),
+        "Every output needs exactly one seed"
+    );
+    let roots: Vec<(usize, F)> = outputs
+        .iter()
+        .zip(seeds)
+        .map(|(output, &seed)| (output.index(), seed))
+        .collect();
+    backward_from(&roots, 0, &mut no_checkpoints);
+}
+
+/// Jacobian of the vector-valued `f` at `inputs`, one row per output and
+/// one column per input. `f` is evaluated once, every row costs one
+/// backward pass.
+pub fn jacobian<F, G>(f: G, inputs: &[F]) -> Matrix<F>
+where
+    F: Float,
+    G: Fn(&[Value<F>]) -> Vec<Value<F>>,
+{
+    let _scope = tape::Scope::<F>::default();
+    let mark = tape::len::<F>();
+    let leaves: Vec<Value<F>> = inputs.iter().map(|&x| Value::leaf(x)).collect();
+    let outputs = f(&leaves);
+
+    let mut seeds = vec![F::ZERO; outputs.len()];
+    let mut data = Vec::with_capacity(outputs.len() * leaves.len());
+    for row in 0..outputs.len() {
+        with_tape::<F, _>(|tape| tape.zero_grads(mark));
+        seeds[row] = F::ONE;
+        backward(&outputs, &seeds);
+        seeds[row] = F::ZERO;
+        data.extend(leaves.iter().map(Value::grad));
+    }
+    Matrix::new(outputs.len(), leaves.len(), data)
+}
+
+/// Hessian of the scalar `f` at `inputs`.
+///
+/// The gradient is built as a graph once and differentiated again for every
+/// row, so `n` inputs take `n` backward passes and `n` squared floats in
+/// total. `hvp` is the scalable alternative.
+pub fn hessian<F, G>(f: G, inputs: &[F]) -> Matrix<F>
+where
+    F: Float,
+    G: Fn(&[Value<F>]) -> Value<F>,
+{
+    let _scope = tape::Scope::<F>::default();
+    let leaves: Vec<Value<F>> = inputs.iter().map(|&x| Value::leaf(x)).collect();
+    let output = f(&leaves);
+    let gradient = graph_grad(&[output], &leaves);
+
+    let mut data = Vec::with_capacity(leaves.len() * leaves.len());
+    for partial in &gradient {
+        data.extend(numeric_grad(&[*partial], &leaves));
+    }
+    Matrix::new(leaves.len(), leaves.len(), data)
+}
+
+/// Hessian-vector product `H(inputs) * vector` of the scalar `f`, without
+/// forming the Hessian: the gradient graph is dotted with `vector` and
+/// differentiated once more. Costs about two extra passes over the graph of
+/// `f`, regardless of the number of inputs.
+pub fn hvp<F, G>(f: G, inputs: &[F], vector: &[F]) -> Vec<F>
+where
+    F: Float,
+    G: Fn(&[Value<F>]) -> Value<F>,
+{
+    assert_eq!(
+        inputs.len(),
+        vector.len(),
+        "Point and vector must have equal length"
+    );
+
+    let _scope = tape::Scope::<F>::default();
+    let leaves: Vec<Value<F>> = inputs.iter().map(|&x| Value::leaf(x)).collect();
+    let output = f(&leaves);
+    let products: Vec<Value<F>> = graph_grad(&[output], &leaves)
+        .iter()
+        .zip(vector)
+        .map(|(partial, &v)| partial * v)
+        .collect();
+    numeric_grad(&products, &leaves)
+}
+
+/// Gradients of the sum of `outputs` with respect to each of `inputs`.
+///
+/// Unlike `Value::backward` nothing is written to the `grad` fields. With
+/// `create_graph` the gradients are built out of regular operations on the
+/// tape, so they can be differentiated again (Hessian-vector products,
+/// gradient penalties, ...). Otherwise they are returned as constants.
+/// Inputs that don't require grad get a gradient of zero.
+pub fn grad<F: Float>(
+    outputs: &[Value<F>],
+    inputs: &[Value<F>],
+    create_graph: bool,
+) -> Vec<Value<F>> {
+    if create_graph {
+        graph_grad(outputs, inputs)
+    } else {
+        numeric_grad(outputs, inputs)
+            .into_iter()
+            .map(Value::constant_leaf)
+            .collect()
+    }
+}
+
+fn numeric_grad<F: Float>(outputs: &[Value<F>], inputs: &[Value<F>]) -> Vec<F> {
+    let Some(root) = outputs.iter().map(Value::index).max() else {
+        return vec![F::ZERO; inputs.len()];
+    };
+
+    let detect_anomaly = tape::is_anomaly_detection_enabled();
+    with_tape::<F, _>(|tape| {
+        let mut grads = vec![F::ZERO; root + 1];
+        let mut reachable = vec![false; root + 1];
+        for output in outputs {
+            grads[output.0] += F::ONE;
+            reachable[output.0] = tape.nodes[output.0].requires_grad;
+        }
+
+        let mut contributions = Vec::new();
+        for node in (0..=root).rev() {
+            if !reachable[node] {
+                continue;
+            }
+            if let Operation::CheckpointOutput = tape.nodes[node].op {
+                no_checkpoints(node);
+            }
+
+            contributions.clear();
+            local_gradients(tape, node, grads[node], &mut contributions);
+            if detect_anomaly {
+                check_gradients(tape, node, grads[node], &contributions, &reachable);
+            }
+            for &(parent, grad) in &contributions {
+                if tape.nodes[parent].requires_grad {
+                    grads[parent] += grad;
+                }
+            }
+
+            for &parent in tape.parents(node) {
+                reachable[parent] |= tape.nodes[parent].requires_grad;
+            }
+        }
+
+        inputs
+            .iter()
+            .map(|input| grads.get(tape.index(input)).copied().unwrap_or(F::ZERO))
+            .collect()
+    })
+}
+
+fn graph_grad<F: Float>(outputs: &[Value<F>], inputs: &[Value<F>]) -> Vec<Value<F>> {
+    let Some(root) = outputs.iter().map(Value::index).max() else {
+        return inputs
+            .iter()
+            .map(|_| Value::constant_leaf(F::ZERO))
+            .collect();
+    };
+
+    // Gradient nodes are appended above `root`, so the walk below only ever
+    // visits the original graph.
+    let mut adjoints: Vec<Option<Value<F>>> = vec![None; root + 1];
+    for output in outputs.iter().filter(|output| output.requires_grad()) {
+        accumulate(&mut adjoints[output.0], Value::constant_leaf(F::ONE));
+    }
+
+    for node in (0..=root).rev() {
+        let Some(adjoint) = adjoints[node] else {
+            continue;
+        };
+        let node = with_tape::<F, _>(|tape| Value::at(tape, node));
+        for (parent, grad) in local_gradient_values(node, &adjoint) {
+            if with_tape::<F, _>(|tape| tape.nodes[parent].requires_grad) {
+                accumulate(&mut adjoints[parent], grad);
+            }
+        }
+    }
+
+    inputs
+        .iter()
+        .map(|input| {
+            adjoints
+                .get(input.index())
+                .copied()
+                .flatten()
+                .unwrap_or_else(|| Value::constant_leaf(F::ZERO))
+        })
+        .collect()
+}
+
+fn accumulate<F: Float>(slot: &mut Option<Value<F>>, grad: Value<F>) {
+    *slot = Some(match slot {
+        Some(sum) => &*sum + &grad,
+        None => grad,
+    });
+}
+
+/// Same rules as `local_gradients`, expressed as operations on the tape.
+fn local_gradient_values<F: Float>(node: Value<F>, grad: &Value<F>) -> Vec<(usize, Value<F>)> {
+    let (op, parents) = with_tape::<F, _>(|tape| {
+        let parents: Vec<Value<F>> = tape
+            .parents(node.0)
+            .iter()
+            .map(|&parent| Value::at(tape, parent))
+            .collect();
+        (tape.nodes[node.0].op, parents)
+    });
+    let out = &node;
+
+    match (op, parents.as_slice()) {
+        (Operation::Add, &[a, b]) => vec![(a.0, *grad), (b.0, *grad)],
+        (Operation::Mul, &[a, b]) => vec![(a.0, grad * &b), (b.0, grad * &a)],
+        (Operation::Div, &[a, b]) if b.value() != F::ZERO => {
+            vec![(a.0, grad / &b), (b.0, &(-grad) * &(&a / &(&b * &b)))]
+        }
+        (Operation::Pow, &[a, b]) => {
+            let mut grads = vec![(a.0, &(grad * &b) * &(&a ^ &(&b - F::ONE)))];
+            if a.value() > F::ZERO {
+                grads.push((b.0, &(grad * out) * &a.ln()));
+            }
+            grads
+        }
+        (Operation::Log, &[a]) if a.value() > F::ZERO => vec![(a.0, grad / &a)],
+        (Operation::Exp, &[a]) => vec![(a.0, grad * out)],
+        (Operation::Tanh, &[a]) => {
+            vec![(a.0, grad * &(&Value::constant_leaf(F::ONE) - &(out * out)))]
+        }
+        (Operation::Sqrt, &[a]) if out.value() > F::ZERO => {
+            vec![(a.0, grad / &(out * F::from_f64(2.0)))]
+        }
+        (Operation::Sigmoid, &[a]) => {
+            vec![(a.0, &(grad * out) * &(&Value::constant_leaf(F::ONE) - out))]
+        }
+        (Operation::Sin, &[a]) => vec![(a.0, grad * &a.cos())],
+        (Operation::Cos, &[a]) => vec![(a.0, &(-grad) * &a.sin())],
+        (Operation::Tan, &[a]) => vec![(a.0, grad * &(&(out * out) + F::ONE))],
+        (Operation::Atan2, &[y, x]) if x.value() != F::ZERO || y.value() != F::ZERO => {
+            let r2 = &(&x * &x) + &(&y * &y);
+            vec![(y.0, &(grad * &x) / &r2), (x.0, &(-grad) * &(&y / &r2))]
+        }
+        (Operation::Sinh, &[a]) => vec![(a.0, grad * &a.cosh())],
+        (Operation::Cosh, &[a]) => vec![(a.0, grad * &a.sinh())],
+        (Operation::Max, &[a, b]) => {
+            let picked = if a.value() >= b.value() { a } else { b };
+            vec![(picked.0, *grad)]
+        }
+        (Operation::Min, &[a, b]) => {
+            let picked = if a.value() <= b.value() { a } else { b };
+            vec![(picked.0, *grad)]
+        }
+        (Operation::Abs, &[a]) if a.value() > F::ZERO => vec![(a.0, *grad)],
+        (Operation::Abs, &[a]) if a.value() < F::ZERO => vec![(a.0, -grad)],
+        (Operation::Clamp, &[x, min, max]) => {
+            let picked = if x.value() < min.value() {
+                min
+            } else if x.value() > max.value() {
+                max
+            } else {
+                x
+            };
+            vec![(picked.0, *grad)]
+        }
+        (Operation::Select, &[cond, a, b]) => {
+            let picked = if cond.value() > F::ZERO { a } else { b };
+            vec![(picked.0, *grad)]
+        }
+        (Operation::Sum, parents) => parents.iter().map(|parent| (parent.0, *grad)).collect(),
+        (Operation::Mean, parents) => {
+            let share = grad / F::from_f64(parents.len() as f64);
+            parents.iter().map(|parent| (parent.0, share)).collect()
+        }
+        (Operation::Dot, parents) => {
+            let (a, b) = parents.split_at(parents.len() / 2);
+            a.iter()
+                .zip(b)
+                .flat_map(|(x, y)| [(x.0, grad * y), (y.0, grad * x)])
+                .collect()
+        }
+        (Operation::CheckpointOutput, _) => {
+            no_checkpoints(node.0);
+            Vec::new()
+        }
+        (Operation::Custom(slot), parents) => {
+            let op = with_tape::<F, _>(|tape| tape.custom_op(slot).clone());
+            let partials = op.backward_graph(parents, out).unwrap_or_else(|| {
+                let inputs: Vec<F> = parents.iter().map(Value::value).collect();
+                op.backward(&inputs, out.value())
+                    .into_iter()
+                    .map(Value::constant_leaf)
+                    .collect()
+            });
+            assert_eq!(
+                partials.len(),
+                parents.len(),
+                "CustomOp::backward returned the wrong number of partials"
+            );
+            parents
+                .iter()
+                .zip(partials)
+                .map(|(parent, partial)| (parent.0, grad * &partial))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
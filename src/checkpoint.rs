@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::tape::{self, with_tape};
+use crate::value::{Operation, Value, backward_from};
+
+type SegmentFn<'a, F> = Rc<dyn Fn(&Checkpoints<'a, F>) -> Vec<Value<F>> + 'a>;
+
+struct Segment<'a, F: Float> {
+    anchor: usize,
+    outputs: usize,
+    f: SegmentFn<'a, F>,
+}
+
+/// Gradient checkpointing, trading compute for memory.
+///
+/// `segment` runs a piece of the computation and throws away everything it
+/// recorded except its outputs. `backward` runs the piece again when the
+/// gradients reach it and backpropagates through the fresh copy, which is
+/// discarded right after. Only one segment's intermediates are alive at a
+/// time.
+///
+/// Segments have to compute the same thing every time they run. Values they
+/// capture from outside, such as parameters, receive their gradients as
+/// usual. A segment gets the checkpoints it belongs to, to nest segments in.
+///
+/// ```
+/// # use grad::{Checkpoints, Value};
+/// let x = Value::new(2.0);
+/// let checkpoints = Checkpoints::new();
+/// let y = checkpoints.segment(|_| vec![&(&x * &x) * &x])[0];
+/// checkpoints.backward(&(&y + 1.0));
+/// assert_eq!(x.grad(), 12.0);
+/// ```
+pub struct Checkpoints<'a, F: Float = f64> {
+    segments: RefCell<Vec<Segment<'a, F>>>,
+}
+
+impl<'a, F: Float> Checkpoints<'a, F> {
+    pub fn new() -> Self {
+        Checkpoints {
+            segments: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Runs `f` and returns its outputs, which must be computed inside it.
+    /// With graph construction turned off this is just `f(self)`.
+    pub fn segment(&self, f: impl Fn(&Checkpoints<'a, F>) -> Vec<Value<F>> + 'a) -> Vec<Value<F>> {
+        if !tape::is_grad_enabled() {
+            return f(self);
+        }
+
+        // Outputs have to be fresh nodes, both here and when recomputed.
+        let _plain = tape::Optimize::disabled();
+        let start = tape::len::<F>();
+        let outputs = f(self);
+        assert!(
+            outputs.iter().all(|output| output.0 >= start),
+            "The outputs of a segment have to be computed inside it"
+        );
+
+        let values: Vec<F> = outputs.iter().map(Value::value).collect();
+        let anchor = with_tape::<F, _>(|tape| {
+            let requires_grad = (start..tape.nodes.len())
+                .flat_map(|node| tape.parents(node))
+                .any(|&parent| parent < start && tape.nodes[parent].requires_grad);
+
+            tape.truncate(start);
+            let anchor = tape.push(F::ZERO, Operation::Checkpoint, []);
+            tape.nodes[anchor].requires_grad = requires_grad;
+            for value in values {
+                tape.push(value, Operation::CheckpointOutput, [anchor]);
+            }
+            anchor
+        });
+
+        let mut segments = self.segments.borrow_mut();
+        segments.retain(|segment| segment.anchor < start);
+        segments.push(Segment {
+            anchor,
+            outputs: outputs.len(),
+            f: Rc::new(f),
+        });
+        with_tape::<F, _>(|tape| {
+            (anchor + 1..anchor + 1 + outputs.len())
+                .map(|node| Value::at(tape, node))
+                .collect()
+        })
+    }
+
+    /// Like `Value::backward`, for graphs containing segments of these
+    /// checkpoints.
+    pub fn backward(&self, output: &Value<F>) {
+        backward_from(&[(output.index(), F::ONE)], 0, &mut |anchor| {
+            self.recompute(anchor)
+        });
+    }
+
+    /// Backpropagates through a fresh run of the segment behind `anchor` and
+    /// returns the nodes from before it that received gradients.
+    fn recompute(&self, anchor: usize) -> Vec<usize> {
+        let (outputs, f) = {
+            let segments = self.segments.borrow();
+            let segment = segments
+                .iter()
+                .rev()
+                .find(|segment| segment.anchor == anchor)
+                .expect("Checkpoint from a different Checkpoints");
+            (segment.outputs, segment.f.clone())
+        };
+        let grads: Vec<F> = with_tape::<F, _>(|tape| {
+            (anchor + 1..anchor + 1 + outputs)
+                .map(|node| tape.nodes[node].grad)
+                .collect()
+        });
+
+        let _scope = tape::Scope::<F>::default();
+        let _plain = tape::Optimize::disabled();
+        let floor = tape::len::<F>();
+        let recomputed = f(self);
+        assert_eq!(
+            recomputed.len(),
+            outputs,
+            "A segment returned a different number of outputs when recomputed"
+        );
+        let roots: Vec<(usize, F)> = recomputed
+            .iter()
+            .zip(grads)
+            .filter(|(output, _)| output.0 >= floor)
+            .map(|(output, grad)| (output.0, grad))
+            .collect();
+        let reachable = backward_from(&roots, floor, &mut |anchor| self.recompute(anchor));
+        self.segments
+            .borrow_mut()
+            .retain(|segment| segment.anchor < floor);
+
+        (0..reachable.len().min(anchor))
+            .filter(|&node| reachable[node])
+            .collect()
+    }
+}
+
+impl<F: Float> Default for Checkpoints<'_, F> {
+    fn default() -> Self {
+        Checkpoints::new()
+    }
+}
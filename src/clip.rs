@@ -0,0 +1,53 @@
+use crate::float::Float;
+
+/// How parameter gradients are clipped before each update.
+///
+/// Clipping is applied to the gradients collected after `backward`, the
+/// gradients flowing through the graph itself are never modified.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum GradientClip {
+    /// Use the exact gradients.
+    #[default]
+    None,
+    /// Clamp every gradient to `[-max, max]`.
+    Value(f64),
+    /// Rescale all gradients so their combined L2 norm is at most `max`.
+    GlobalNorm(f64),
+    /// Rescale the gradients of every layer so its L2 norm is at most `max`.
+    LayerNorm(f64),
+}
+
+impl GradientClip {
+    /// Clips `grads` in place. `layer_sizes` holds the number of parameters
+    /// of each layer, in the order the gradients are laid out.
+    pub fn apply<F: Float>(&self, grads: &mut [F], layer_sizes: &[usize]) {
+        match *self {
+            GradientClip::None => {}
+            GradientClip::Value(max) => {
+                let max = F::from_f64(max);
+                for grad in grads {
+                    *grad = grad.clamp(-max, max);
+                }
+            }
+            GradientClip::GlobalNorm(max) => clip_norm(grads, max),
+            GradientClip::LayerNorm(max) => {
+                let mut offset = 0;
+                for &size in layer_sizes {
+                    clip_norm(&mut grads[offset..offset + size], max);
+                    offset += size;
+                }
+            }
+        }
+    }
+}
+
+fn clip_norm<F: Float>(grads: &mut [F], max: f64) {
+    let max = F::from_f64(max);
+    let norm = grads.iter().map(|&g| g * g).sum::<F>().sqrt();
+    if norm > max {
+        let scale = max / norm;
+        for grad in grads {
+            *grad *= scale;
+        }
+    }
+}
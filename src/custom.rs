@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use crate::float::Float;
+use crate::tape::{is_grad_enabled, with_tape};
+use crate::value::Value;
+
+/// A user-defined differentiable operation on scalars of type `F`.
+///
+/// The operation is recorded on the tape like a built-in one, so it takes
+/// part in `Value::backward`, `autograd::grad`, DOT export and gradcheck.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use grad::{CustomOp, Value};
+/// struct LogSumExp;
+///
+/// impl CustomOp for LogSumExp {
+///     fn name(&self) -> &str {
+///         "LogSumExp"
+///     }
+///
+///     fn forward(&self, inputs: &[f64]) -> f64 {
+///         let max = inputs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+///         max + inputs.iter().map(|x| (x - max).exp()).sum::<f64>().ln()
+///     }
+///
+///     fn backward(&self, inputs: &[f64], output: f64) -> Vec<f64> {
+///         inputs.iter().map(|x| (x - output).exp()).collect()
+///     }
+/// }
+///
+/// let (a, b) = (Value::new(1.0), Value::new(2.0));
+/// let lse = Arc::new(LogSumExp);
+/// let y = Value::custom(&lse, &[&a, &b]);
+/// y.backward();
+/// assert!((a.grad() + b.grad() - 1.0).abs() < 1e-12);
+/// ```
+pub trait CustomOp<F: Float = f64> {
+    /// Name shown in DOT exports.
+    fn name(&self) -> &str;
+
+    fn forward(&self, inputs: &[F]) -> F;
+
+    /// Partial derivatives of the output with respect to every input.
+    fn backward(&self, inputs: &[F], output: F) -> Vec<F>;
+
+    /// Same partial derivatives as `backward`, built as operations on the
+    /// tape. Used by `autograd::grad` with `create_graph`, when `None` the
+    /// partials from `backward` are treated as constants.
+    fn backward_graph(&self, _inputs: &[Value<F>], _output: &Value<F>) -> Option<Vec<Value<F>>> {
+        None
+    }
+}
+
+impl<F: Float> Value<F> {
+    pub fn custom<T: CustomOp<F> + 'static>(op: &Arc<T>, inputs: &[&Value<F>]) -> Value<F> {
+        let values: Vec<F> = inputs.iter().map(|input| input.value()).collect();
+        let result = op.forward(&values);
+        if !is_grad_enabled() {
+            return Value::untracked_leaf(result);
+        }
+        let op: Arc<dyn CustomOp<F>> = op.clone();
+        with_tape::<F, _>(|tape| {
+            let inputs: Vec<usize> = inputs.iter().map(|input| tape.index(input)).collect();
+            let index = tape.push_custom(result, op, inputs);
+            Value::at(tape, index)
+        })
+    }
+}
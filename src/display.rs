@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+
+use crate::autograd;
+use crate::float::Float;
+use crate::tape::{self, Tape, with_tape};
+use crate::value::{Operation, Value};
+
+// How tightly an expression binds, operands binding less tightly than their
+// operator requires are put in parentheses.
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const NEGATION: u8 = 3;
+const POWER: u8 = 4;
+const ATOM: u8 = 5;
+
+type Rendered = (String, u8);
+
+impl<F: Float> Value<F> {
+    /// Gives this value a name to be printed with instead of its value or
+    /// expression.
+    pub fn set_name(&self, name: impl Into<String>) {
+        with_tape::<F, _>(|tape| tape.names.insert(tape.index(self), name.into()));
+    }
+
+    pub fn name(&self) -> Option<String> {
+        with_tape::<F, _>(|tape| tape.names.get(&tape.index(self)).cloned())
+    }
+
+    /// The derivative of this value with respect to `leaf`, built as an
+    /// expression on the tape with the simplifications of `tape::Optimize`.
+    /// Its value is the gradient `backward` would give `leaf`, printing it
+    /// shows the expression.
+    ///
+    /// ```
+    /// # use grad::Value;
+    /// let x = Value::new(2.0);
+    /// x.set_name("x");
+    /// let y = (&x * &x).sin();
+    /// assert_eq!(y.to_string(), "sin(x * x)");
+    /// assert_eq!(y.derivative(&x).to_string(), "cos(x * x) * x + cos(x * x) * x");
+    /// ```
+    pub fn derivative(&self, leaf: &Value<F>) -> Value<F> {
+        let _optimize = tape::Optimize::new();
+        autograd::grad(&[*self], &[*leaf], true)[0]
+    }
+}
+
+/// Prints the expression that computed the value, down to named values and
+/// leaves, which are printed by name or value. Values used more than once
+/// are printed at every use, so this is meant for small expressions.
+/// Operands longer than `MAX_OPERAND` characters are shortened to `…`.
+impl<F: Float> fmt::Display for Value<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (expression, _) = with_tape::<F, _>(|tape| render(tape, tape.index(self)));
+        f.write_str(&expression)
+    }
+}
+
+/// Longest operand printed in full. Every node is rendered from its
+/// operands, so this also bounds the length of the whole expression by a
+/// multiple of it, however often subexpressions are repeated.
+const MAX_OPERAND: usize = 500;
+
+/// A rendered node, and the same without its minus sign if it has one, so
+/// sums can print it as a subtraction.
+struct Node {
+    rendered: Rendered,
+    negated: Option<Rendered>,
+}
+
+fn render<F: Float>(tape: &Tape<F>, root: usize) -> Rendered {
+    // Nodes below the root and the last node using each of them. Parents
+    // come before their children on the tape, so rendering in tape order
+    // finds every operand already rendered, and it can be dropped after its
+    // last use.
+    let mut last_use = HashMap::from([(root, root)]);
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if tape.names.contains_key(&node) {
+            continue;
+        }
+        for &parent in tape.parents(node) {
+            match last_use.entry(parent) {
+                Entry::Vacant(entry) => {
+                    entry.insert(node);
+                    stack.push(parent);
+                }
+                Entry::Occupied(mut entry) => {
+                    let last = entry.get_mut();
+                    *last = (*last).max(node);
+                }
+            }
+        }
+    }
+    let mut nodes: Vec<usize> = last_use.keys().copied().collect();
+    nodes.sort_unstable();
+
+    let mut rendered: HashMap<usize, Node> = HashMap::new();
+    for node in nodes {
+        let rendered_node = render_node(tape, node, &rendered);
+        if !tape.names.contains_key(&node) {
+            for parent in tape.parents(node) {
+                if last_use[parent] == node {
+                    rendered.remove(parent);
+                }
+            }
+        }
+        rendered.insert(node, rendered_node);
+    }
+    rendered.remove(&root).unwrap().rendered
+}
+
+fn render_node<F: Float>(tape: &Tape<F>, node: usize, rendered: &HashMap<usize, Node>) -> Node {
+    if let Some(name) = tape.names.get(&node) {
+        return Node {
+            rendered: (name.clone(), ATOM),
+            negated: None,
+        };
+    }
+    let parents = tape.parents(node);
+    let operand = |i: usize| shorten(&rendered[&parents[i]].rendered);
+    let call = |name: &str| {
+        let operands: Vec<String> = (0..parents.len()).map(|i| operand(i).0).collect();
+        (format!("{}({})", name, operands.join(", ")), ATOM)
+    };
+    let minus_one = parents.iter().position(|&p| is_minus_one(tape, p));
+
+    let value = tape.nodes[node].value;
+    let expression = match tape.nodes[node].op {
+        Operation::Add | Operation::Sum => sum(parents, rendered),
+        Operation::Mul => match minus_one {
+            Some(i) => negation(operand(1 - i)),
+            None => product(operand(0), operand(1)),
+        },
+        Operation::Div => (
+            format!(
+                "{} / {}",
+                wrap(operand(0), PRODUCT),
+                wrap(operand(1), POWER)
+            ),
+            PRODUCT,
+        ),
+        Operation::Pow => (
+            format!("{}^{}", wrap(operand(0), ATOM), wrap(operand(1), ATOM)),
+            POWER,
+        ),
+        Operation::Exp => (format!("e^{}", wrap(operand(0), ATOM)), POWER),
+        Operation::Log => call("ln"),
+        Operation::Tanh => call("tanh"),
+        Operation::Sqrt => call("sqrt"),
+        Operation::Sigmoid => call("sigmoid"),
+        Operation::Sin => call("sin"),
+        Operation::Cos => call("cos"),
+        Operation::Tan => call("tan"),
+        Operation::Atan2 => call("atan2"),
+        Operation::Sinh => call("sinh"),
+        Operation::Cosh => call("cosh"),
+        Operation::Max => call("max"),
+        Operation::Min => call("min"),
+        Operation::Abs => call("abs"),
+        Operation::Clamp => call("clamp"),
+        Operation::Select => call("select"),
+        Operation::Mean => call("mean"),
+        Operation::Detach => call("detach"),
+        Operation::Custom(slot) => call(tape.custom_op(slot).name()),
+        Operation::Dot => {
+            let half = parents.len() / 2;
+            let terms: Vec<String> = (0..half)
+                .map(|i| product(operand(i), operand(half + i)).0)
+                .collect();
+            join(terms)
+        }
+        Operation::Checkpoint
+        | Operation::CheckpointOutput
+        | Operation::Constant
+        | Operation::None => number(value),
+    };
+
+    let negated = match tape.nodes[node].op {
+        Operation::Mul => minus_one.map(|i| operand(1 - i)),
+        Operation::Constant | Operation::None if value < F::ZERO => Some(number(-value)),
+        _ => None,
+    };
+    Node {
+        rendered: expression,
+        negated,
+    }
+}
+
+/// Writes subtracted terms, added negations and negative numbers, as `a - b`.
+fn sum(terms: &[usize], rendered: &HashMap<usize, Node>) -> Rendered {
+    let mut expression = String::new();
+    for (i, term) in terms.iter().enumerate() {
+        let term = &rendered[term];
+        match (i, &term.negated) {
+            (0, _) => expression = shorten(&term.rendered).0,
+            (_, Some(negated)) => {
+                expression = format!("{} - {}", expression, wrap(shorten(negated), PRODUCT));
+            }
+            (_, None) => {
+                expression = format!(
+                    "{} + {}",
+                    expression,
+                    wrap(shorten(&term.rendered), PRODUCT)
+                );
+            }
+        }
+    }
+    if expression.is_empty() {
+        ("0".to_string(), ATOM)
+    } else {
+        (expression, SUM)
+    }
+}
+
+fn join(terms: Vec<String>) -> Rendered {
+    if terms.is_empty() {
+        ("0".to_string(), ATOM)
+    } else {
+        (terms.join(" + "), SUM)
+    }
+}
+
+fn shorten(operand: &Rendered) -> Rendered {
+    if operand.0.chars().count() > MAX_OPERAND {
+        ("…".to_string(), ATOM)
+    } else {
+        operand.clone()
+    }
+}
+
+fn product(a: Rendered, b: Rendered) -> Rendered {
+    // Products and quotients on the right may go without parentheses, the
+    // result is the same.
+    let b = if b.1 == NEGATION {
+        format!("({})", b.0)
+    } else {
+        wrap(b, PRODUCT)
+    };
+    (format!("{} * {}", wrap(a, PRODUCT), b), PRODUCT)
+}
+
+fn negation(x: Rendered) -> Rendered {
+    (format!("-{}", wrap(x, POWER)), NEGATION)
+}
+
+fn number<F: Float>(value: F) -> Rendered {
+    let precedence = if value < F::ZERO { NEGATION } else { ATOM };
+    (value.to_string(), precedence)
+}
+
+fn is_minus_one<F: Float>(tape: &Tape<F>, node: usize) -> bool {
+    matches!(tape.nodes[node].op, Operation::Constant) && tape.nodes[node].value == -F::ONE
+}
+
+fn wrap((expression, precedence): Rendered, min: u8) -> String {
+    if precedence < min {
+        format!("({})", expression)
+    } else {
+        expression
+    }
+}
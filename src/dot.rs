@@ -0,0 +1,95 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+use crate::float::Float;
+use crate::tape::with_tape;
+use crate::value::{Operation, Value};
+
+/// Options for `Value::to_dot`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DotOptions {
+    /// Fold constant leaves into the label of the node using them.
+    pub collapse_constants: bool,
+    /// Only draw nodes at most this many edges away from the root. Nodes
+    /// whose inputs were cut off are drawn dashed.
+    pub max_depth: Option<usize>,
+}
+
+impl<F: Float> Value<F> {
+    /// Renders the graph leading up to this value in Graphviz DOT format,
+    /// labelling every node with its operation, value and gradient.
+    pub fn to_dot(&self, options: &DotOptions) -> String {
+        with_tape::<F, _>(|tape| {
+            let root = tape.index(self);
+            let is_collapsed = |node: usize| {
+                options.collapse_constants && matches!(tape.nodes[node].op, Operation::Constant)
+            };
+
+            // Breadth-first, so every node is reached along its shortest path.
+            let mut depths = HashMap::from([(root, 0)]);
+            let mut order = Vec::new();
+            let mut queue = VecDeque::from([root]);
+            while let Some(node) = queue.pop_front() {
+                order.push(node);
+                let depth = depths[&node];
+                if options.max_depth.is_some_and(|max| depth >= max) {
+                    continue;
+                }
+                for &parent in tape.parents(node) {
+                    if !is_collapsed(parent) && !depths.contains_key(&parent) {
+                        depths.insert(parent, depth + 1);
+                        queue.push_back(parent);
+                    }
+                }
+            }
+            order.sort_unstable();
+
+            let mut dot = String::from("digraph {\n    rankdir=LR;\n    node [shape=record];\n");
+            for &node in &order {
+                let data = &tape.nodes[node];
+                let mut label = format!(
+                    "{{ #{} {} | value {} | grad {}",
+                    node,
+                    escape(&tape.op_name(node)),
+                    escape(&format!("{:.4}", data.value)),
+                    escape(&format!("{:.4}", data.grad))
+                );
+                let mut truncated = false;
+                for &parent in tape.parents(node) {
+                    if is_collapsed(parent) {
+                        let value = format!("{:.4}", tape.nodes[parent].value);
+                        write!(label, " | const {}", escape(&value)).unwrap();
+                    } else if !depths.contains_key(&parent) {
+                        truncated = true;
+                    }
+                }
+                label.push_str(" }");
+
+                let style = if truncated { ", style=dashed" } else { "" };
+                writeln!(dot, "    n{} [label=\"{}\"{}];", node, label, style).unwrap();
+            }
+            for &node in &order {
+                for &parent in tape.parents(node) {
+                    if depths.contains_key(&parent) {
+                        writeln!(dot, "    n{} -> n{};", parent, node).unwrap();
+                    }
+                }
+            }
+            dot.push_str("}\n");
+            dot
+        })
+    }
+}
+
+/// Escapes the characters that structure record labels, so names and values
+/// show up as written.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,308 @@
+use std::ops;
+
+// Forward-mode automatic differentiation. A dual number carries a value and
+// its derivative along one direction, every operation applies the chain rule
+// immediately, so no graph is recorded. Dual numbers are `f64` only.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual {
+    pub value: f64,
+    pub derivative: f64,
+}
+
+impl Dual {
+    pub fn new(value: f64, derivative: f64) -> Self {
+        Dual { value, derivative }
+    }
+
+    pub fn constant(value: f64) -> Self {
+        Dual::new(value, 0.0)
+    }
+
+    pub fn variable(value: f64) -> Self {
+        Dual::new(value, 1.0)
+    }
+
+    fn chain(self, value: f64, local_grad: f64) -> Dual {
+        Dual::new(value, local_grad * self.derivative)
+    }
+
+    /// Has a derivative of zero outside its domain, like `Value::ln`.
+    pub fn ln(self) -> Dual {
+        let local_grad = if self.value > 0.0 {
+            1.0 / self.value
+        } else {
+            0.0
+        };
+        self.chain(self.value.ln(), local_grad)
+    }
+
+    pub fn exp(self) -> Dual {
+        let result = self.value.exp();
+        self.chain(result, result)
+    }
+
+    pub fn tanh(self) -> Dual {
+        let result = self.value.tanh();
+        self.chain(result, 1.0 - result * result)
+    }
+
+    /// Has a derivative of zero at zero and below, like `Value::sqrt`.
+    pub fn sqrt(self) -> Dual {
+        let result = self.value.sqrt();
+        let local_grad = if result > 0.0 { 0.5 / result } else { 0.0 };
+        self.chain(result, local_grad)
+    }
+
+    pub fn sigmoid(self) -> Dual {
+        let x = self.value;
+        let result = if x >= 0.0 {
+            1.0 / (1.0 + (-x).exp())
+        } else {
+            let e = x.exp();
+            e / (1.0 + e)
+        };
+        self.chain(result, result * (1.0 - result))
+    }
+
+    pub fn sin(self) -> Dual {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+
+    pub fn cos(self) -> Dual {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+
+    pub fn tan(self) -> Dual {
+        let result = self.value.tan();
+        self.chain(result, 1.0 + result * result)
+    }
+
+    /// Has a derivative of zero at the origin, like `Value::atan2`.
+    pub fn atan2(self, other: Dual) -> Dual {
+        let (y, x) = (self.value, other.value);
+        let r2 = x * x + y * y;
+        let derivative = if r2 != 0.0 {
+            (x * self.derivative - y * other.derivative) / r2
+        } else {
+            0.0
+        };
+        Dual::new(y.atan2(x), derivative)
+    }
+
+    pub fn sinh(self) -> Dual {
+        self.chain(self.value.sinh(), self.value.cosh())
+    }
+
+    pub fn cosh(self) -> Dual {
+        self.chain(self.value.cosh(), self.value.sinh())
+    }
+
+    pub fn max(self, other: Dual) -> Dual {
+        if self.value >= other.value {
+            self
+        } else {
+            other
+        }
+    }
+
+    pub fn min(self, other: Dual) -> Dual {
+        if self.value <= other.value {
+            self
+        } else {
+            other
+        }
+    }
+
+    /// Has a derivative of zero at zero, like `Value::abs`.
+    pub fn abs(self) -> Dual {
+        let sign = if self.value > 0.0 {
+            1.0
+        } else if self.value < 0.0 {
+            -1.0
+        } else {
+            0.0
+        };
+        self.chain(self.value.abs(), sign)
+    }
+
+    pub fn clamp(self, min: f64, max: f64) -> Dual {
+        if self.value < min {
+            Dual::constant(min)
+        } else if self.value > max {
+            Dual::constant(max)
+        } else {
+            self
+        }
+    }
+}
+
+impl ops::Add for Dual {
+    type Output = Dual;
+
+    fn add(self, other: Dual) -> Dual {
+        Dual::new(self.value + other.value, self.derivative + other.derivative)
+    }
+}
+
+impl ops::Add<f64> for Dual {
+    type Output = Dual;
+
+    fn add(self, other: f64) -> Dual {
+        self + Dual::constant(other)
+    }
+}
+
+impl ops::Add<Dual> for f64 {
+    type Output = Dual;
+
+    fn add(self, other: Dual) -> Dual {
+        Dual::constant(self) + other
+    }
+}
+
+impl ops::Sub for Dual {
+    type Output = Dual;
+
+    fn sub(self, other: Dual) -> Dual {
+        self + (-other)
+    }
+}
+
+impl ops::Sub<f64> for Dual {
+    type Output = Dual;
+
+    fn sub(self, other: f64) -> Dual {
+        self + (-other)
+    }
+}
+
+impl ops::Sub<Dual> for f64 {
+    type Output = Dual;
+
+    fn sub(self, other: Dual) -> Dual {
+        self + (-other)
+    }
+}
+
+impl ops::Mul for Dual {
+    type Output = Dual;
+
+    fn mul(self, other: Dual) -> Dual {
+        Dual::new(
+            self.value * other.value,
+            self.derivative * other.value + self.value * other.derivative,
+        )
+    }
+}
+
+impl ops::Mul<f64> for Dual {
+    type Output = Dual;
+
+    fn mul(self, other: f64) -> Dual {
+        self * Dual::constant(other)
+    }
+}
+
+impl ops::Mul<Dual> for f64 {
+    type Output = Dual;
+
+    fn mul(self, other: Dual) -> Dual {
+        Dual::constant(self) * other
+    }
+}
+
+impl ops::Div for Dual {
+    type Output = Dual;
+
+    /// Has a derivative of zero when dividing by zero, like `Value`.
+    fn div(self, other: Dual) -> Dual {
+        let derivative = if other.value != 0.0 {
+            (self.derivative * other.value - self.value * other.derivative)
+                / (other.value * other.value)
+        } else {
+            0.0
+        };
+        Dual::new(self.value / other.value, derivative)
+    }
+}
+
+impl ops::Div<f64> for Dual {
+    type Output = Dual;
+
+    fn div(self, other: f64) -> Dual {
+        self / Dual::constant(other)
+    }
+}
+
+impl ops::Div<Dual> for f64 {
+    type Output = Dual;
+
+    fn div(self, other: Dual) -> Dual {
+        Dual::constant(self) / other
+    }
+}
+
+impl ops::BitXor for Dual {
+    type Output = Dual;
+
+    fn bitxor(self, other: Dual) -> Dual {
+        let result = self.value.powf(other.value);
+        let mut derivative = other.value * self.value.powf(other.value - 1.0) * self.derivative;
+        if self.value > 0.0 {
+            derivative += result * self.value.ln() * other.derivative;
+        }
+        Dual::new(result, derivative)
+    }
+}
+
+impl ops::BitXor<f64> for Dual {
+    type Output = Dual;
+
+    fn bitxor(self, other: f64) -> Dual {
+        self ^ Dual::constant(other)
+    }
+}
+
+impl ops::BitXor<Dual> for f64 {
+    type Output = Dual;
+
+    fn bitxor(self, other: Dual) -> Dual {
+        Dual::constant(self) ^ other
+    }
+}
+
+impl ops::Neg for Dual {
+    type Output = Dual;
+
+    fn neg(self) -> Dual {
+        Dual::new(-self.value, -self.derivative)
+    }
+}
+
+impl From<f64> for Dual {
+    fn from(value: f64) -> Self {
+        Dual::constant(value)
+    }
+}
+
+/// Evaluates `f` at `x` and returns its outputs together with the
+/// Jacobian-vector product `J(x) * v`, in a single forward pass.
+pub fn jvp<F>(f: F, x: &[f64], v: &[f64]) -> (Vec<f64>, Vec<f64>)
+where
+    F: Fn(&[Dual]) -> Vec<Dual>,
+{
+    assert_eq!(
+        x.len(),
+        v.len(),
+        "Point and direction must have equal length"
+    );
+
+    let inputs: Vec<Dual> = x.iter().zip(v).map(|(&x, &v)| Dual::new(x, v)).collect();
+    let outputs = f(&inputs);
+
+    outputs
+        .iter()
+        .map(|output| (output.value, output.derivative))
+        .unzip()
+}
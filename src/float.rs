@@ -0,0 +1,149 @@
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Scalar type the engine computes in, implemented for `f32` and `f64`.
+///
+/// Every precision records its graphs on a tape of its own, a `Value<f32>`
+/// and a `Value<f64>` never share nodes.
+pub trait Float:
+    private::Sealed
+    + Copy
+    + Default
+    + Debug
+    + Display
+    + PartialOrd
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + Sum
+    + Serialize
+    + DeserializeOwned
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const NEG_INFINITY: Self;
+
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn ln(self) -> Self;
+    fn exp(self) -> Self;
+    fn tanh(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn powf(self, exponent: Self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn sinh(self) -> Self;
+    fn cosh(self) -> Self;
+    fn abs(self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn clamp(self, min: Self, max: Self) -> Self;
+    fn is_finite(self) -> bool;
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+macro_rules! impl_float {
+    ($t:ident) => {
+        impl Float for $t {
+            const ZERO: Self = 0.0;
+            const ONE: Self = 1.0;
+            const NEG_INFINITY: Self = $t::NEG_INFINITY;
+
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn ln(self) -> Self {
+                $t::ln(self)
+            }
+
+            fn exp(self) -> Self {
+                $t::exp(self)
+            }
+
+            fn tanh(self) -> Self {
+                $t::tanh(self)
+            }
+
+            fn sqrt(self) -> Self {
+                $t::sqrt(self)
+            }
+
+            fn powf(self, exponent: Self) -> Self {
+                $t::powf(self, exponent)
+            }
+
+            fn sin(self) -> Self {
+                $t::sin(self)
+            }
+
+            fn cos(self) -> Self {
+                $t::cos(self)
+            }
+
+            fn tan(self) -> Self {
+                $t::tan(self)
+            }
+
+            fn atan2(self, other: Self) -> Self {
+                $t::atan2(self, other)
+            }
+
+            fn sinh(self) -> Self {
+                $t::sinh(self)
+            }
+
+            fn cosh(self) -> Self {
+                $t::cosh(self)
+            }
+
+            fn abs(self) -> Self {
+                $t::abs(self)
+            }
+
+            fn max(self, other: Self) -> Self {
+                $t::max(self, other)
+            }
+
+            fn min(self, other: Self) -> Self {
+                $t::min(self, other)
+            }
+
+            fn clamp(self, min: Self, max: Self) -> Self {
+                $t::clamp(self, min, max)
+            }
+
+            fn is_finite(self) -> bool {
+                $t::is_finite(self)
+            }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
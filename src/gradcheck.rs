@@ -0,0 +1,146 @@
+use std::fmt;
+
+use crate::float::Float;
+use crate::tape;
+use crate::value::Value;
+
+/// Result of comparing one analytic gradient with its numeric estimate.
+#[derive(Clone, Copy, Debug)]
+pub struct GradCheckEntry<F: Float = f64> {
+    pub analytic: F,
+    pub numeric: F,
+    /// `|analytic - numeric| / max(|analytic|, |numeric|, 1)`, zero when both
+    /// agree exactly and NaN when either is not finite. The floor of one
+    /// makes this the absolute error for gradients near zero, where the
+    /// rounding noise of the finite differences would otherwise dominate.
+    pub relative_error: F,
+}
+
+#[derive(Clone, Debug)]
+pub struct GradCheckReport<F: Float = f64> {
+    /// One entry per input, in input order.
+    pub entries: Vec<GradCheckEntry<F>>,
+    pub tolerance: F,
+}
+
+impl<F: Float> GradCheckReport<F> {
+    fn within_tolerance(&self, entry: &GradCheckEntry<F>) -> bool {
+        entry.relative_error <= self.tolerance
+    }
+
+    pub fn passed(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| self.within_tolerance(entry))
+    }
+
+    pub fn max_relative_error(&self) -> F {
+        let max = self
+            .entries
+            .iter()
+            .map(|entry| entry.relative_error.to_f64())
+            .fold(0.0, |max: f64, error| {
+                if max.is_nan() || error.is_nan() {
+                    f64::NAN
+                } else {
+                    max.max(error)
+                }
+            });
+        F::from_f64(max)
+    }
+
+    /// Indices of the inputs whose relative error exceeds the tolerance.
+    pub fn failures(&self) -> Vec<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !self.within_tolerance(entry))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl<F: Float> fmt::Display for GradCheckReport<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>6} {:>14} {:>14} {:>10}",
+            "input", "analytic", "numeric", "rel error"
+        )?;
+        for (i, entry) in self.entries.iter().enumerate() {
+            let marker = if self.within_tolerance(entry) {
+                ""
+            } else {
+                "  <-"
+            };
+            writeln!(
+                f,
+                "{:>6} {:>14.6e} {:>14.6e} {:>10.2e}{}",
+                i,
+                entry.analytic.to_f64(),
+                entry.numeric.to_f64(),
+                entry.relative_error.to_f64(),
+                marker
+            )?;
+        }
+        write!(
+            f,
+            "max relative error {:.2e} (tolerance {:.2e})",
+            self.max_relative_error().to_f64(),
+            self.tolerance.to_f64()
+        )
+    }
+}
+
+/// Checks the gradients `Value::backward` computes for `f` at `inputs`
+/// against central finite differences with step `epsilon`.
+///
+/// `f` is called once for the analytic gradients and twice per input for
+/// the numeric ones, every call gets freshly created leaves.
+pub fn gradcheck<F, G>(f: G, inputs: &[F], epsilon: F, tolerance: F) -> GradCheckReport<F>
+where
+    F: Float,
+    G: Fn(&[Value<F>]) -> Value<F>,
+{
+    let analytic: Vec<F> = {
+        let _scope = tape::Scope::<F>::default();
+        let leaves: Vec<Value<F>> = inputs.iter().map(|&x| Value::leaf(x)).collect();
+        f(&leaves).backward();
+        leaves.iter().map(Value::grad).collect()
+    };
+
+    let evaluate = |point: &[F]| {
+        let _scope = tape::Scope::<F>::default();
+        let leaves: Vec<Value<F>> = point.iter().map(|&x| Value::leaf(x)).collect();
+        f(&leaves).value()
+    };
+
+    let mut point = inputs.to_vec();
+    let entries = analytic
+        .into_iter()
+        .enumerate()
+        .map(|(i, analytic)| {
+            point[i] = inputs[i] + epsilon;
+            let plus = evaluate(&point);
+            point[i] = inputs[i] - epsilon;
+            let minus = evaluate(&point);
+            point[i] = inputs[i];
+
+            let numeric = (plus - minus) / (F::from_f64(2.0) * epsilon);
+            let relative_error = if analytic == numeric {
+                F::ZERO
+            } else {
+                let scale = analytic.abs().max(numeric.abs()).max(F::ONE);
+                (analytic - numeric).abs() / scale
+            };
+
+            GradCheckEntry {
+                analytic,
+                numeric,
+                relative_error,
+            }
+        })
+        .collect();
+
+    GradCheckReport { entries, tolerance }
+}
@@ -0,0 +1,46 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::float::Float;
+use crate::tape::with_tape;
+use crate::value::Value;
+
+impl<F: Float> Value<F> {
+    /// Calls `hook` with the gradient of this value during `backward`, once
+    /// all of it has been accumulated and before it is passed on to the
+    /// inputs. The returned gradient replaces it, so a hook can log it
+    /// (return it unchanged), clip it or flip its sign.
+    ///
+    /// Hooks on the same value run in registration order. They are dropped
+    /// together with the value when the tape is truncated.
+    ///
+    /// Only `backward` and `Plan::backward` run hooks. `autograd::grad`,
+    /// `hessian`, `hvp` and `Value::derivative` differentiate the graph as
+    /// recorded and ignore them.
+    ///
+    /// ```
+    /// # use grad::Value;
+    /// let x = Value::new(3.0);
+    /// let y = &x * &x;
+    /// y.register_hook(|grad| -grad);
+    /// (&y + 1.0).backward();
+    /// assert_eq!(x.grad(), -6.0);
+    /// ```
+    pub fn register_hook(&self, hook: impl FnMut(F) -> F + 'static) {
+        assert!(
+            self.requires_grad(),
+            "Cannot register a hook on a value that doesn't require grad"
+        );
+        with_tape::<F, _>(|tape| tape.add_hook(self.0, Rc::new(RefCell::new(hook))));
+    }
+}
+
+/// Runs the hooks of `node` on its accumulated gradient. The tape must not be
+/// borrowed, hooks are free to use it.
+pub(crate) fn run_hooks<F: Float>(node: usize) {
+    let (hooks, mut grad) = with_tape::<F, _>(|tape| (tape.hooks(node), tape.nodes[node].grad));
+    for hook in hooks {
+        grad = (hook.borrow_mut())(grad);
+    }
+    with_tape::<F, _>(|tape| tape.nodes[node].grad = grad);
+}
@@ -1,13 +1,38 @@
 mod activation;
+mod anomaly;
+pub mod autograd;
+mod checkpoint;
+mod clip;
+mod custom;
+mod display;
+mod dot;
+pub mod dual;
+mod float;
+pub mod gradcheck;
+mod hook;
 mod loss;
+mod matrix;
 mod mlp;
 pub mod mnist;
 mod neuron;
 mod operations;
+mod optimize;
+mod plan;
 mod serialization;
+pub mod tape;
+mod tensor;
 pub mod util;
 mod value;
 
 pub use activation::Activation;
+pub use checkpoint::Checkpoints;
+pub use clip::GradientClip;
+pub use custom::CustomOp;
+pub use dot::DotOptions;
+pub use float::Float;
 pub use loss::Loss;
+pub use matrix::Matrix;
 pub use mlp::Model;
+pub use plan::Plan;
+pub use tensor::Tensor;
+pub use value::Value;
@@ -1,5 +1,9 @@
+use crate::float::Float;
 use crate::value::Value;
 
+/// A batch of predictions paired with their targets.
+type Results<F> = Vec<(Vec<Value<F>>, Vec<Value<F>>)>;
+
 #[derive(Clone)]
 pub enum Loss {
     MSE,
@@ -7,47 +11,41 @@ pub enum Loss {
 }
 
 impl Loss {
-    pub fn apply(&self, results: Vec<(Vec<Value>, Vec<Value>)>) -> Value {
+    pub fn apply<F: Float>(&self, results: Results<F>) -> Value<F> {
         match self {
             Loss::MSE => {
-                let squares = results
+                let squares: Vec<Value<F>> = results
                     .iter()
                     .map(|(pred, exp)| {
-                        let diff = pred.iter().zip(exp).map(|(a, b)| a - b).collect::<Vec<_>>();
-                        diff.iter()
-                            .map(|x| x * x)
-                            .fold(Value::new(0.0), |acc, square| &acc + &square)
+                        let diff: Vec<Value<F>> =
+                            pred.iter().zip(exp).map(|(a, b)| a - b).collect();
+                        Value::dot(&diff, &diff)
                     })
-                    .fold(Value::new(0.0), |acc, square| &acc + &square);
-                &squares / &Value::from(results.len() as f64)
+                    .collect();
+                Value::mean(&squares)
             }
             Loss::CrossEntropy => {
-                let mut total_loss = Value::new(0.0);
-                let count = Value::from(results.len() as f64);
-
-                for (pred, target) in results {
-                    let mut batch_loss = Value::new(0.0);
-
-                    for (p, t) in pred.iter().zip(target.iter()) {
-                        let p_clipped = if p.value() < 1e-10 {
-                            Value::new(1e-10)
-                        } else if p.value() > 1.0 - 1e-10 {
-                            Value::new(1.0 - 1e-10)
-                        } else {
-                            p.clone()
-                        };
-
-                        if t.value() > 0.5 {
-                            batch_loss = &batch_loss - &p_clipped.ln();
-                        } else {
-                            batch_loss = &batch_loss - &(1.0 - &p_clipped).ln();
-                        }
-                    }
-
-                    total_loss = &total_loss + &batch_loss;
-                }
+                let losses: Vec<Value<F>> = results
+                    .iter()
+                    .map(|(pred, target)| {
+                        let log_likelihoods: Vec<Value<F>> = pred
+                            .iter()
+                            .zip(target)
+                            .map(|(p, t)| {
+                                let p_clipped =
+                                    p.clamp(F::from_f64(1e-10), F::from_f64(1.0 - 1e-10));
 
-                &total_loss / &count
+                                if t.value() > F::from_f64(0.5) {
+                                    p_clipped.ln()
+                                } else {
+                                    (&Value::constant_leaf(F::ONE) - &p_clipped).ln()
+                                }
+                            })
+                            .collect();
+                        -&Value::sum(&log_likelihoods)
+                    })
+                    .collect();
+                Value::mean(&losses)
             }
         }
     }
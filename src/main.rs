@@ -1,7 +1,7 @@
 use grad::{
     Activation, Loss, Model,
     mnist::{self, print_mnist},
-    util,
+    tape, util,
 };
 
 fn main() {
@@ -18,9 +18,22 @@ fn main() {
         &[Activation::ReLU, Activation::ReLU, Activation::Softmax],
     );
 
+    // `--checkpoint <samples>` keeps only the graph of that many samples at
+    // a time, trading a second forward pass for a smaller tape.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--checkpoint") {
+        let samples = args
+            .get(i + 1)
+            .and_then(|samples| samples.parse().ok())
+            .expect("--checkpoint needs a number of samples per segment");
+        model.set_checkpointing(Some(samples));
+    }
+
+    print_tape_stats("before training");
     let epochs = 100;
     let eta = 0.5;
     model.train(&train_data, epochs, eta, Loss::CrossEntropy);
+    print_tape_stats("after training");
 
     let accuracy = model.evaluate(&test_data);
     println!("\nTest Accuracy: {:.2}%", accuracy * 100.0);
@@ -38,3 +51,15 @@ fn main() {
         print_mnist(img, Some(prediction));
     }
 }
+
+fn print_tape_stats(when: &str) {
+    let stats = tape::stats::<f64>();
+    println!(
+        "Tape {}: {} nodes, peak {} nodes, {} edges, {:.1} MB",
+        when,
+        stats.nodes,
+        stats.peak_nodes,
+        stats.peak_edges,
+        stats.peak_bytes::<f64>() as f64 / 1e6
+    );
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::float::Float;
+
+/// A dense, row-major matrix of plain floats, as returned by
+/// `autograd::jacobian` and `autograd::hessian`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix<F: Float = f64> {
+    rows: usize,
+    cols: usize,
+    data: Vec<F>,
+}
+
+impl<F: Float> Matrix<F> {
+    pub fn new(rows: usize, cols: usize, data: Vec<F>) -> Self {
+        assert_eq!(
+            data.len(),
+            rows * cols,
+            "Data length does not match a {}x{} matrix",
+            rows,
+            cols
+        );
+        Matrix { rows, cols, data }
+    }
+
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Matrix::new(rows, cols, vec![F::ZERO; rows * cols])
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn data(&self) -> &[F] {
+        &self.data
+    }
+
+    pub fn row(&self, row: usize) -> &[F] {
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn transpose(&self) -> Matrix<F> {
+        let mut transposed = Matrix::zeros(self.cols, self.rows);
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                transposed[(j, i)] = self[(i, j)];
+            }
+        }
+        transposed
+    }
+
+    /// Product with the column vector `vector`.
+    pub fn mul_vec(&self, vector: &[F]) -> Vec<F> {
+        assert_eq!(vector.len(), self.cols, "Vector length mismatch");
+        (0..self.rows)
+            .map(|i| self.row(i).iter().zip(vector).map(|(&a, &b)| a * b).sum())
+            .collect()
+    }
+}
+
+impl<F: Float> Index<(usize, usize)> for Matrix<F> {
+    type Output = F;
+
+    fn index(&self, (row, col): (usize, usize)) -> &F {
+        assert!(
+            row < self.rows && col < self.cols,
+            "Index ({}, {}) out of bounds",
+            row,
+            col
+        );
+        &self.data[row * self.cols + col]
+    }
+}
+
+impl<F: Float> IndexMut<(usize, usize)> for Matrix<F> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut F {
+        assert!(
+            row < self.rows && col < self.cols,
+            "Index ({}, {}) out of bounds",
+            row,
+            col
+        );
+        &mut self.data[row * self.cols + col]
+    }
+}
+
+impl<F: Float> fmt::Display for Matrix<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..self.rows {
+            for x in self.row(i) {
+                write!(f, "{:>12.6}", x)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
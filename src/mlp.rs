@@ -1,15 +1,22 @@
+use crate::gradcheck::{GradCheckReport, gradcheck};
+use crate::neuron::{BoundNeuron, Neuron};
 use crate::serialization::{ModelData, NeuronData};
-use crate::{Loss, activation::Activation, neuron::Neuron, value::Value};
+use crate::{
+    Checkpoints, Float, GradientClip, Loss, Matrix, Plan, activation::Activation, autograd, tape,
+    value::Value,
+};
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
+use std::thread;
 
-struct MLP {
-    pub layers: Vec<Vec<Neuron>>,
+#[allow(clippy::upper_case_acronyms)]
+struct MLP<F: Float> {
+    pub layers: Vec<Vec<Neuron<F>>>,
     pub layer_activations: Vec<Activation>,
 }
 
-impl MLP {
-    pub fn new(sizes: &[usize], activations: &[Activation]) -> MLP {
+impl<F: Float> MLP<F> {
+    pub fn new(sizes: &[usize], activations: &[Activation]) -> Self {
         assert_eq!(
             sizes.len() - 1,
             activations.len(),
@@ -28,38 +35,66 @@ impl MLP {
         }
     }
 
-    pub fn forward(&self, input: Vec<Value>) -> Vec<Value> {
-        let mut output = input;
-        for (layer, phi) in self.layers.iter().zip(self.layer_activations.to_owned()) {
-            output = layer.iter().map(|neuron| neuron.forward(&output)).collect();
-            output = phi.apply(&output)
-        }
-        output
-    }
-
-    fn params(&self) -> Vec<&Value> {
+    pub fn params(&self) -> Vec<F> {
         self.layers
             .iter()
             .flatten()
-            .flat_map(|neuron| neuron.params())
+            .flat_map(Neuron::params)
             .collect()
     }
 
-    pub fn update(&mut self, eta: f64) {
-        for layer in &mut self.layers {
-            for neuron in layer {
-                neuron.update(eta);
-            }
+    pub fn bind(&self) -> BoundMLP<'_, F> {
+        let params: Vec<Value<F>> = self.params().into_iter().map(Value::leaf).collect();
+        self.bind_with(&params)
+    }
+
+    /// Builds the network on top of the given parameter values, laid out like
+    /// `params`.
+    pub fn bind_with(&self, params: &[Value<F>]) -> BoundMLP<'_, F> {
+        let mut offset = 0;
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .map(|neuron| {
+                        let count = neuron.weights.len() + 1;
+                        let bound = neuron.bind(&params[offset..offset + count]);
+                        offset += count;
+                        bound
+                    })
+                    .collect()
+            })
+            .collect();
+
+        BoundMLP {
+            layers,
+            layer_activations: &self.layer_activations,
         }
     }
 
-    pub fn zero_grad(&mut self) {
-        for param in self.params() {
-            param.zero_grad();
+    pub fn update(&mut self, grads: &[F], eta: F) {
+        let mut offset = 0;
+        for neuron in self.layers.iter_mut().flatten() {
+            let count = neuron.weights.len() + 1;
+            neuron.update(&grads[offset..offset + count], eta);
+            offset += count;
         }
     }
 
-    pub fn to_data(&self) -> ModelData {
+    pub fn params_count(&self) -> usize {
+        self.layer_params_counts().iter().sum()
+    }
+
+    pub fn layer_params_counts(&self) -> Vec<usize> {
+        self.layers
+            .iter()
+            .map(|layer| layer.iter().map(|neuron| neuron.weights.len() + 1).sum())
+            .collect()
+    }
+
+    pub fn to_data(&self) -> ModelData<F> {
         let input_size = if !self.layers.is_empty() && !self.layers[0].is_empty() {
             self.layers[0][0].weights.len()
         } else {
@@ -81,8 +116,8 @@ impl MLP {
                     layer
                         .iter()
                         .map(|neuron| NeuronData {
-                            weights: neuron.weights.iter().map(|w| w.value()).collect(),
-                            bias: neuron.bias.value(),
+                            weights: neuron.weights.clone(),
+                            bias: neuron.bias,
                         })
                         .collect()
                 })
@@ -90,18 +125,14 @@ impl MLP {
         }
     }
 
-    pub fn from_data(data: ModelData) -> Self {
+    pub fn from_data(data: ModelData<F>) -> Self {
         let mut mlp = MLP::new(&data.layer_sizes, &data.activations);
 
         for (layer_idx, layer_data) in data.layers.iter().enumerate() {
             for (neuron_idx, neuron_data) in layer_data.iter().enumerate() {
                 let neuron = &mut mlp.layers[layer_idx][neuron_idx];
-
-                for (w_idx, &value) in neuron_data.weights.iter().enumerate() {
-                    neuron.weights[w_idx].update_value(value);
-                }
-
-                neuron.bias.update_value(neuron_data.bias);
+                neuron.weights.clone_from(&neuron_data.weights);
+                neuron.bias = neuron_data.bias;
             }
         }
 
@@ -109,12 +140,106 @@ impl MLP {
     }
 }
 
-pub struct Model {
-    mlp: MLP,
+/// The parameters of an `MLP` recorded on the current thread's tape.
+struct BoundMLP<'a, F: Float> {
+    layers: Vec<Vec<BoundNeuron<F>>>,
+    layer_activations: &'a [Activation],
+}
+
+impl<F: Float> BoundMLP<'_, F> {
+    pub fn forward(&self, input: Vec<Value<F>>) -> Vec<Value<F>> {
+        let mut output = input;
+        for (layer, phi) in self.layers.iter().zip(self.layer_activations) {
+            output = layer.iter().map(|neuron| neuron.forward(&output)).collect();
+            output = phi.apply(&output)
+        }
+        output
+    }
+
+    fn params(&self) -> Vec<&Value<F>> {
+        self.layers
+            .iter()
+            .flatten()
+            .flat_map(|neuron| neuron.params())
+            .collect()
+    }
+
+    /// Gradients of all parameters, in the order expected by `MLP::update`.
+    pub fn gradients(&self) -> Vec<F> {
+        self.params().iter().map(|param| param.grad()).collect()
+    }
+}
+
+fn batch_loss<F: Float>(
+    bound: &BoundMLP<F>,
+    data: &[(Vec<F>, Vec<F>)],
+    loss_type: &Loss,
+) -> Value<F> {
+    let mut results = Vec::new();
+
+    for (input, target) in data {
+        let input_values = input.iter().map(|&x| Value::untracked_leaf(x)).collect();
+        let target_values = target.iter().map(|&x| Value::untracked_leaf(x)).collect();
+
+        let pred = bound.forward(input_values);
+
+        results.push((pred, target_values));
+    }
+
+    loss_type.apply(results)
+}
+
+/// Runs the forward pass and loss over `data` on the current thread's tape
+/// and returns the loss together with the parameter gradients. With
+/// `checkpoint` set, every that many samples form a checkpointed segment.
+fn loss_and_gradients<F: Float>(
+    mlp: &MLP<F>,
+    data: &[(Vec<F>, Vec<F>)],
+    loss_type: &Loss,
+    checkpoint: Option<usize>,
+) -> (F, Vec<F>) {
+    let _scope = tape::Scope::<F>::default();
+    let bound = mlp.bind();
+    let Some(samples_per_segment) = checkpoint else {
+        let loss = batch_loss(&bound, data, loss_type);
+        loss.backward();
+        return (loss.value(), bound.gradients());
+    };
+
+    // The losses are means over the samples, so the chunks are weighted by
+    // their share of them.
+    let checkpoints = Checkpoints::new();
+    let total = F::from_f64(data.len() as f64);
+    let bound = &bound;
+    let parts: Vec<Value<F>> = data
+        .chunks(samples_per_segment)
+        .map(|chunk| {
+            let weight = F::from_f64(chunk.len() as f64) / total;
+            checkpoints.segment(move |_| vec![&batch_loss(bound, chunk, loss_type) * weight])[0]
+        })
+        .collect();
+    let loss = Value::sum(&parts);
+    checkpoints.backward(&loss);
+
+    (loss.value(), bound.gradients())
+}
+
+/// A trained or trainable network.
+///
+/// Parameters are stored as plain floats and only recorded on a tape for the
+/// duration of a forward pass, so a `Model` is `Send + Sync` and can be shared
+/// between threads. Every thread builds its graphs on its own tape.
+///
+/// The parameters are `f64` unless another `Float` is chosen, `cast` converts
+/// a model between precisions.
+pub struct Model<F: Float = f64> {
+    mlp: MLP<F>,
     input_size: usize,
+    gradient_clip: GradientClip,
+    checkpoint: Option<usize>,
 }
 
-impl Model {
+impl<F: Float> Model<F> {
     pub fn new(layer_sizes: &[usize], activations: &[Activation]) -> Self {
         assert!(
             layer_sizes.len() >= 2,
@@ -124,47 +249,161 @@ impl Model {
         Self {
             mlp: MLP::new(layer_sizes, activations),
             input_size: layer_sizes[0],
+            gradient_clip: GradientClip::None,
+            checkpoint: None,
         }
     }
 
+    /// Sets the clipping applied to the parameter gradients during training.
+    pub fn set_gradient_clip(&mut self, clip: GradientClip) {
+        if let GradientClip::Value(max)
+        | GradientClip::GlobalNorm(max)
+        | GradientClip::LayerNorm(max) = clip
+        {
+            assert!(
+                max >= 0.0,
+                "Gradient clip bound must be a non-negative number, got {}",
+                max
+            );
+        }
+        self.gradient_clip = clip;
+    }
+
+    /// Splits the training data into checkpointed segments of this many
+    /// samples, see `Checkpoints`. Only one segment's activations are kept at
+    /// a time, which bounds the memory used by training at the cost of a
+    /// second forward pass. `None` turns it off.
+    pub fn set_checkpointing(&mut self, samples_per_segment: Option<usize>) {
+        assert!(
+            samples_per_segment != Some(0),
+            "Segments need at least one sample"
+        );
+        self.checkpoint = samples_per_segment;
+    }
+
+    /// Full-batch gradient descent. The loss over `training_data` is traced
+    /// into a `Plan` once and replayed with the updated parameters every
+    /// epoch. With checkpointing the graph is rebuilt every epoch instead.
     pub fn train(
         &mut self,
-        training_data: &[(Vec<f64>, Vec<f64>)],
+        training_data: &[(Vec<F>, Vec<F>)],
         epochs: usize,
-        learning_rate: f64,
+        learning_rate: F,
         loss_type: Loss,
     ) {
-        for epoch in 0..epochs {
-            self.mlp.zero_grad();
-            let mut results = Vec::new();
+        if epochs == 0 || training_data.is_empty() {
+            return;
+        }
 
-            for (input, target) in training_data {
-                let input_values = input.iter().map(|&x| Value::from(x)).collect();
-                let target_values = target.iter().map(|&x| Value::from(x)).collect();
+        let mlp = &self.mlp;
+        let mut plan = self.checkpoint.is_none().then(|| {
+            Plan::trace(
+                |params| batch_loss(&mlp.bind_with(params), training_data, &loss_type),
+                &mlp.params(),
+            )
+        });
 
-                let pred = self.mlp.forward(input_values);
+        for epoch in 0..epochs {
+            let (loss, mut grads) = match &mut plan {
+                Some(plan) => (plan.forward(&self.mlp.params()), plan.backward()),
+                None => loss_and_gradients(&self.mlp, training_data, &loss_type, self.checkpoint),
+            };
+            self.clip_gradients(&mut grads);
+            self.mlp.update(&grads, learning_rate);
+
+            println!("Epoch {:3} => Loss: {:.6}", epoch + 1, loss);
+        }
+    }
 
-                results.push((pred, target_values));
-            }
+    /// Data-parallel variant of `train`.
+    ///
+    /// Every epoch the training data is split into `threads` chunks, each
+    /// chunk is differentiated on its own thread and the gradients are
+    /// averaged, which gives the same update as a full-batch step.
+    pub fn train_parallel(
+        &mut self,
+        training_data: &[(Vec<F>, Vec<F>)],
+        epochs: usize,
+        learning_rate: F,
+        loss_type: Loss,
+        threads: usize,
+    ) {
+        assert!(threads > 0, "Need at least one thread");
+        let chunk_size = training_data.len().div_ceil(threads).max(1);
+        let total = F::from_f64(training_data.len() as f64);
 
-            let loss = loss_type.apply(results);
-            loss.backward();
-            self.mlp.update(learning_rate);
+        for epoch in 0..epochs {
+            let mlp = &self.mlp;
+            let loss_type = &loss_type;
+            let detect_anomaly = tape::is_anomaly_detection_enabled();
+            let checkpoint = self.checkpoint;
+
+            let (loss, mut grads) = thread::scope(|s| {
+                let handles: Vec<_> = training_data
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        s.spawn(move || {
+                            let _anomaly = detect_anomaly.then(tape::DetectAnomaly::new);
+                            let (loss, grads) =
+                                loss_and_gradients(mlp, chunk, loss_type, checkpoint);
+                            (loss, grads, F::from_f64(chunk.len() as f64) / total)
+                        })
+                    })
+                    .collect();
+
+                let mut loss = F::ZERO;
+                let mut grads = vec![F::ZERO; mlp.params_count()];
+                for handle in handles {
+                    let (chunk_loss, chunk_grads, weight) =
+                        handle.join().expect("Training thread panicked");
+                    loss += weight * chunk_loss;
+                    for (grad, chunk_grad) in grads.iter_mut().zip(chunk_grads) {
+                        *grad += weight * chunk_grad;
+                    }
+                }
+                (loss, grads)
+            });
 
-            println!("Epoch {:3} => Loss: {:.6}", epoch + 1, loss.value());
+            self.clip_gradients(&mut grads);
+            self.mlp.update(&grads, learning_rate);
+
+            println!("Epoch {:3} => Loss: {:.6}", epoch + 1, loss);
         }
     }
 
-    pub fn predict(&self, input: &[f64]) -> Vec<f64> {
+    fn clip_gradients(&self, grads: &mut [F]) {
+        self.gradient_clip
+            .apply(grads, &self.mlp.layer_params_counts());
+    }
+
+    /// Runs the network with graph construction turned off, see `tape::NoGrad`.
+    pub fn predict(&self, input: &[F]) -> Vec<F> {
         assert_eq!(input.len(), self.input_size, "Input size mismatch");
-        let input_values = input.iter().map(|&x| Value::from(x)).collect();
+        let _scope = tape::Scope::<F>::default();
+        let _no_grad = tape::NoGrad::new();
+        let input_values = input.iter().map(|&x| Value::untracked_leaf(x)).collect();
 
-        let output_values = self.mlp.forward(input_values);
+        let output_values = self.mlp.bind().forward(input_values);
 
         output_values.iter().map(|v| v.value()).collect()
     }
 
-    pub fn predict_class(&self, input: &[f64]) -> usize {
+    /// Jacobian of the outputs with respect to `input`, one row per output.
+    pub fn jacobian(&self, input: &[F]) -> Matrix<F> {
+        assert_eq!(input.len(), self.input_size, "Input size mismatch");
+        let _scope = tape::Scope::<F>::default();
+        let params: Vec<Value<F>> = self
+            .mlp
+            .params()
+            .into_iter()
+            .map(Value::untracked_leaf)
+            .collect();
+        let bound = self.mlp.bind_with(&params);
+
+        autograd::jacobian(|input| bound.forward(input.to_vec()), input)
+    }
+
+    pub fn predict_class(&self, input: &[F]) -> usize {
         let output = self.predict(input);
 
         output
@@ -175,7 +414,7 @@ impl Model {
             .unwrap_or(0)
     }
 
-    pub fn evaluate(&self, test_data: &[(Vec<f64>, Vec<f64>)]) -> f64 {
+    pub fn evaluate(&self, test_data: &[(Vec<F>, Vec<F>)]) -> f64 {
         let mut correct = 0;
         let total = test_data.len();
 
@@ -197,6 +436,59 @@ impl Model {
         correct as f64 / total as f64
     }
 
+    /// Converts the parameters to another precision.
+    pub fn cast<G: Float>(&self) -> Model<G> {
+        let data = self.mlp.to_data();
+        let data = ModelData {
+            layer_sizes: data.layer_sizes,
+            activations: data.activations,
+            layers: data
+                .layers
+                .into_iter()
+                .map(|layer| {
+                    layer
+                        .into_iter()
+                        .map(|neuron| NeuronData {
+                            weights: neuron
+                                .weights
+                                .into_iter()
+                                .map(|w| G::from_f64(w.to_f64()))
+                                .collect(),
+                            bias: G::from_f64(neuron.bias.to_f64()),
+                        })
+                        .collect()
+                })
+                .collect(),
+        };
+
+        Model {
+            mlp: MLP::from_data(data),
+            input_size: self.input_size,
+            gradient_clip: self.gradient_clip,
+            checkpoint: self.checkpoint,
+        }
+    }
+
+    /// Hessian of the loss over `data` with respect to all parameters, laid
+    /// out like the gradients. Quadratic in the parameter count, only
+    /// feasible for small models.
+    pub fn hessian(&self, data: &[(Vec<F>, Vec<F>)], loss_type: Loss) -> Matrix<F> {
+        autograd::hessian(
+            |params| batch_loss(&self.mlp.bind_with(params), data, &loss_type),
+            &self.mlp.params(),
+        )
+    }
+
+    /// Product of the loss Hessian with `vector`, see `autograd::hvp`. Linear
+    /// in the parameter count.
+    pub fn hvp(&self, data: &[(Vec<F>, Vec<F>)], loss_type: Loss, vector: &[F]) -> Vec<F> {
+        autograd::hvp(
+            |params| batch_loss(&self.mlp.bind_with(params), data, &loss_type),
+            &self.mlp.params(),
+            vector,
+        )
+    }
+
     pub fn save(&self, path: &str) -> std::io::Result<()> {
         let data = self.mlp.to_data();
         let file = File::create(path)?;
@@ -205,10 +497,12 @@ impl Model {
         Ok(())
     }
 
+    /// Loads a model saved by `save`. Files store plain JSON numbers, so a
+    /// model saved in one precision can be loaded in another.
     pub fn load(path: &str) -> std::io::Result<Self> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
-        let data: ModelData = serde_json::from_reader(reader)?;
+        let data: ModelData<F> = serde_json::from_reader(reader)?;
 
         let input_size = if !data.layer_sizes.is_empty() {
             data.layer_sizes[0]
@@ -218,6 +512,29 @@ impl Model {
 
         let mlp = MLP::from_data(data);
 
-        Ok(Model { mlp, input_size })
+        Ok(Model {
+            mlp,
+            input_size,
+            gradient_clip: GradientClip::None,
+            checkpoint: None,
+        })
+    }
+
+    /// Compares the backpropagated gradient of every parameter against
+    /// central finite differences of the loss over `data`. Every parameter
+    /// costs two extra passes over the data, so keep the batch small.
+    pub fn gradcheck(
+        &self,
+        data: &[(Vec<F>, Vec<F>)],
+        loss_type: Loss,
+        epsilon: F,
+        tolerance: F,
+    ) -> GradCheckReport<F> {
+        gradcheck(
+            |params| batch_loss(&self.mlp.bind_with(params), data, &loss_type),
+            &self.mlp.params(),
+            epsilon,
+            tolerance,
+        )
     }
 }
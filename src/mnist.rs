@@ -10,6 +10,8 @@ pub struct MNISTSample {
     pub one_hot: Vec<f64>,
 }
 
+// Lines that fail to read are skipped rather than ending the parse.
+#[allow(clippy::lines_filter_map_ok)]
 pub fn parse_mnist<P>(filename: P) -> Result<Vec<MNISTSample>, io::Error>
 where
     P: AsRef<Path>,
@@ -1,44 +1,74 @@
 use rand::Rng;
 
+use crate::float::Float;
 use crate::value::Value;
 
-pub(crate) struct Neuron {
-    pub weights: Vec<Value>,
-    pub bias: Value,
+pub(crate) struct Neuron<F: Float> {
+    pub weights: Vec<F>,
+    pub bias: F,
 }
 
-impl Neuron {
+/// A neuron whose parameters have been recorded on the current thread's tape.
+pub(crate) struct BoundNeuron<F: Float> {
+    pub weights: Vec<Value<F>>,
+    pub bias: Value<F>,
+}
+
+impl<F: Float> Neuron<F> {
     pub fn new(n: usize) -> Self {
         let mut rng = rand::thread_rng();
         let scale = 1.0 / (n as f64).sqrt();
         Neuron {
             weights: (0..n)
-                .map(|_| Value::new(rng.gen_range(-scale..scale)))
+                .map(|_| F::from_f64(rng.gen_range(-scale..scale)))
                 .collect(),
-            bias: Value::new(rng.gen_range(-0.1..0.1)),
+            bias: F::from_f64(rng.gen_range(-0.1..0.1)),
         }
     }
 
-    pub fn forward(&self, inputs: &[Value]) -> Value {
-        let mut sum = 0.0.into();
-        for (weight, input) in self.weights.iter().zip(inputs) {
-            sum = &sum + &(weight * input);
+    /// Wraps parameters already recorded on the tape, laid out like `params`.
+    pub fn bind(&self, params: &[Value<F>]) -> BoundNeuron<F> {
+        assert_eq!(
+            params.len(),
+            self.weights.len() + 1,
+            "Parameter count mismatch"
+        );
+        let (bias, weights) = params.split_last().unwrap();
+        BoundNeuron {
+            weights: weights.to_vec(),
+            bias: *bias,
         }
-        &sum + &self.bias
     }
 
-    pub fn params(&self) -> Vec<&Value> {
+    pub fn params(&self) -> impl Iterator<Item = F> + '_ {
         self.weights
             .iter()
-            .chain(std::iter::once(&self.bias))
-            .collect()
+            .copied()
+            .chain(std::iter::once(self.bias))
     }
 
-    pub fn update(&mut self, eta: f64) {
-        for w in &mut self.weights {
-            w.update_value(w.value() - eta * w.grad());
+    pub fn params_mut(&mut self) -> impl Iterator<Item = &mut F> {
+        self.weights
+            .iter_mut()
+            .chain(std::iter::once(&mut self.bias))
+    }
+
+    pub fn update(&mut self, grads: &[F], eta: F) {
+        for (param, &grad) in self.params_mut().zip(grads) {
+            *param -= eta * grad;
         }
-        self.bias
-            .update_value(self.bias.value() - eta * self.bias.grad());
+    }
+}
+
+impl<F: Float> BoundNeuron<F> {
+    pub fn forward(&self, inputs: &[Value<F>]) -> Value<F> {
+        &Value::dot(&self.weights, inputs) + &self.bias
+    }
+
+    pub fn params(&self) -> Vec<&Value<F>> {
+        self.weights
+            .iter()
+            .chain(std::iter::once(&self.bias))
+            .collect()
     }
 }
@@ -1,219 +1,188 @@
 use std::ops;
-use std::sync::Arc;
 
-use crate::value::{Operation, Value, ValueData};
+use crate::float::Float;
+use crate::value::{Operation, Value};
 
-// Implementations of add/sub/mul/div/pow for &Value and f64 each.
+// Implementations of add/sub/mul/div/pow for &Value and scalars each.
 // Each operation stores the operation type and operands.
 
-impl ops::Add for &Value {
-    type Output = Value;
+impl<F: Float> ops::Add for &Value<F> {
+    type Output = Value<F>;
 
-    fn add(self, other: &Value) -> Value {
+    fn add(self, other: &Value<F>) -> Value<F> {
         let result = self.value() + other.value();
-        Value(Arc::new(ValueData::new(
-            result,
-            Operation::Add,
-            vec![Arc::clone(&self.0), Arc::clone(&other.0)],
-        )))
+        Value::from_op(result, Operation::Add, &[self, other])
     }
 }
 
-impl ops::Add<f64> for &Value {
-    type Output = Value;
+impl<F: Float> ops::Add<F> for &Value<F> {
+    type Output = Value<F>;
 
-    fn add(self, other: f64) -> Value {
+    fn add(self, other: F) -> Value<F> {
         let result = self.value() + other;
-        Value(Arc::new(ValueData::new(
+        Value::from_op(
             result,
             Operation::Add,
-            vec![
-                Arc::clone(&self.0),
-                Arc::new(ValueData::new(other, Operation::None, vec![])),
-            ],
-        )))
+            &[self, &Value::constant_leaf(other)],
+        )
     }
 }
 
-impl ops::Add<&Value> for f64 {
-    type Output = Value;
+impl<F: Float> ops::Sub for &Value<F> {
+    type Output = Value<F>;
 
-    fn add(self, other: &Value) -> Value {
-        let result = self + other.value();
-        Value(Arc::new(ValueData::new(
-            result,
-            Operation::Add,
-            vec![
-                Arc::new(ValueData::new(self, Operation::None, vec![])),
-                Arc::clone(&other.0),
-            ],
-        )))
-    }
-}
-
-impl ops::Sub for &Value {
-    type Output = Value;
-
-    fn sub(self, other: &Value) -> Value {
+    fn sub(self, other: &Value<F>) -> Value<F> {
         self + &(-other)
     }
 }
 
-impl ops::Sub<f64> for &Value {
-    type Output = Value;
+impl<F: Float> ops::Sub<F> for &Value<F> {
+    type Output = Value<F>;
 
-    fn sub(self, other: f64) -> Value {
+    fn sub(self, other: F) -> Value<F> {
         self + (-other)
     }
 }
 
-impl ops::Sub<&Value> for f64 {
-    type Output = Value;
-
-    fn sub(self, other: &Value) -> Value {
-        self + &(-other)
-    }
-}
-
-impl ops::Mul for &Value {
-    type Output = Value;
+impl<F: Float> ops::Mul for &Value<F> {
+    type Output = Value<F>;
 
-    fn mul(self, other: &Value) -> Value {
+    fn mul(self, other: &Value<F>) -> Value<F> {
         let result = self.value() * other.value();
-        Value(Arc::new(ValueData::new(
-            result,
-            Operation::Mul,
-            vec![Arc::clone(&self.0), Arc::clone(&other.0)],
-        )))
+        Value::from_op(result, Operation::Mul, &[self, other])
     }
 }
 
-impl ops::Mul<f64> for &Value {
-    type Output = Value;
+impl<F: Float> ops::Mul<F> for &Value<F> {
+    type Output = Value<F>;
 
-    fn mul(self, other: f64) -> Value {
+    fn mul(self, other: F) -> Value<F> {
         let result = self.value() * other;
-        Value(Arc::new(ValueData::new(
+        Value::from_op(
             result,
             Operation::Mul,
-            vec![
-                Arc::clone(&self.0),
-                Arc::new(ValueData::new(other, Operation::None, vec![])),
-            ],
-        )))
+            &[self, &Value::constant_leaf(other)],
+        )
     }
 }
 
-impl ops::Mul<&Value> for f64 {
-    type Output = Value;
+impl<F: Float> ops::Div for &Value<F> {
+    type Output = Value<F>;
 
-    fn mul(self, other: &Value) -> Value {
-        let result = self * other.value();
-        Value(Arc::new(ValueData::new(
-            result,
-            Operation::Mul,
-            vec![
-                Arc::new(ValueData::new(self, Operation::None, vec![])),
-                Arc::clone(&other.0),
-            ],
-        )))
-    }
-}
-
-impl ops::Div for &Value {
-    type Output = Value;
-
-    fn div(self, other: &Value) -> Value {
+    fn div(self, other: &Value<F>) -> Value<F> {
         let result = self.value() / other.value();
-        Value(Arc::new(ValueData::new(
-            result,
-            Operation::Div,
-            vec![Arc::clone(&self.0), Arc::clone(&other.0)],
-        )))
+        Value::from_op(result, Operation::Div, &[self, other])
     }
 }
 
-impl ops::Div<&Value> for f64 {
-    type Output = Value;
-
-    fn div(self, other: &Value) -> Value {
-        let result = self / other.value();
-        Value(Arc::new(ValueData::new(
-            result,
-            Operation::Div,
-            vec![
-                Arc::new(ValueData::new(self, Operation::None, vec![])),
-                Arc::clone(&other.0),
-            ],
-        )))
-    }
-}
+impl<F: Float> ops::Div<F> for &Value<F> {
+    type Output = Value<F>;
 
-impl ops::Div<f64> for &Value {
-    type Output = Value;
-
-    fn div(self, other: f64) -> Value {
+    fn div(self, other: F) -> Value<F> {
         let result = self.value() / other;
-        Value(Arc::new(ValueData::new(
+        Value::from_op(
             result,
             Operation::Div,
-            vec![
-                Arc::clone(&self.0),
-                Arc::new(ValueData::new(other, Operation::None, vec![])),
-            ],
-        )))
+            &[self, &Value::constant_leaf(other)],
+        )
     }
 }
 
-impl ops::BitXor for &Value {
-    type Output = Value;
+impl<F: Float> ops::BitXor for &Value<F> {
+    type Output = Value<F>;
 
-    fn bitxor(self, other: &Value) -> Value {
+    fn bitxor(self, other: &Value<F>) -> Value<F> {
         let result = self.value().powf(other.value());
-        Value(Arc::new(ValueData::new(
-            result,
-            Operation::Pow,
-            vec![Arc::clone(&self.0), Arc::clone(&other.0)],
-        )))
+        Value::from_op(result, Operation::Pow, &[self, other])
     }
 }
 
-impl ops::BitXor<f64> for &Value {
-    type Output = Value;
+impl<F: Float> ops::BitXor<F> for &Value<F> {
+    type Output = Value<F>;
 
-    fn bitxor(self, other: f64) -> Value {
+    fn bitxor(self, other: F) -> Value<F> {
         let result = self.value().powf(other);
-        Value(Arc::new(ValueData::new(
+        Value::from_op(
             result,
             Operation::Pow,
-            vec![
-                Arc::clone(&self.0),
-                Arc::new(ValueData::new(other, Operation::None, vec![])),
-            ],
-        )))
-    }
-}
-
-impl ops::BitXor<&Value> for f64 {
-    type Output = Value;
-
-    fn bitxor(self, other: &Value) -> Value {
-        let result = self.powf(other.value());
-        Value(Arc::new(ValueData::new(
-            result,
-            Operation::Pow,
-            vec![
-                Arc::new(ValueData::new(self, Operation::None, vec![])),
-                Arc::clone(&other.0),
-            ],
-        )))
-    }
-}
-
-impl ops::Neg for &Value {
-    type Output = Value;
-
-    fn neg(self) -> Value {
-        self * -1.0
-    }
-}
+            &[self, &Value::constant_leaf(other)],
+        )
+    }
+}
+
+impl<F: Float> ops::Neg for &Value<F> {
+    type Output = Value<F>;
+
+    fn neg(self) -> Value<F> {
+        self * -F::ONE
+    }
+}
+
+// The orphan rule rules out a blanket impl with the scalar on the left, so
+// those are spelled out per precision.
+macro_rules! impl_scalar_lhs {
+    ($t:ty) => {
+        impl ops::Add<&Value<$t>> for $t {
+            type Output = Value<$t>;
+
+            fn add(self, other: &Value<$t>) -> Value<$t> {
+                let result = self + other.value();
+                Value::from_op(
+                    result,
+                    Operation::Add,
+                    &[&Value::constant_leaf(self), other],
+                )
+            }
+        }
+
+        impl ops::Sub<&Value<$t>> for $t {
+            type Output = Value<$t>;
+
+            fn sub(self, other: &Value<$t>) -> Value<$t> {
+                self + &(-other)
+            }
+        }
+
+        impl ops::Mul<&Value<$t>> for $t {
+            type Output = Value<$t>;
+
+            fn mul(self, other: &Value<$t>) -> Value<$t> {
+                let result = self * other.value();
+                Value::from_op(
+                    result,
+                    Operation::Mul,
+                    &[&Value::constant_leaf(self), other],
+                )
+            }
+        }
+
+        impl ops::Div<&Value<$t>> for $t {
+            type Output = Value<$t>;
+
+            fn div(self, other: &Value<$t>) -> Value<$t> {
+                let result = self / other.value();
+                Value::from_op(
+                    result,
+                    Operation::Div,
+                    &[&Value::constant_leaf(self), other],
+                )
+            }
+        }
+
+        impl ops::BitXor<&Value<$t>> for $t {
+            type Output = Value<$t>;
+
+            fn bitxor(self, other: &Value<$t>) -> Value<$t> {
+                let result = self.powf(other.value());
+                Value::from_op(
+                    result,
+                    Operation::Pow,
+                    &[&Value::constant_leaf(self), other],
+                )
+            }
+        }
+    };
+}
+
+impl_scalar_lhs!(f32);
+impl_scalar_lhs!(f64);
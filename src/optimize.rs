@@ -0,0 +1,80 @@
+use crate::float::Float;
+use crate::tape::Tape;
+use crate::value::Operation;
+
+/// What a node computes, for finding one that already computes it.
+#[derive(PartialEq, Eq, Hash)]
+pub(crate) struct Expression {
+    op: Operation,
+    parents: Vec<usize>,
+    // Bits of the value of constants, which have no parents to tell them apart.
+    constant: u64,
+}
+
+/// Records `op` like `Tape::push` does, simplified first (see
+/// `tape::Optimize`). Returns the node holding the result, which may be an
+/// existing one.
+pub(crate) fn push<F: Float>(
+    tape: &mut Tape<F>,
+    value: F,
+    op: Operation,
+    parents: &[usize],
+) -> usize {
+    if let Some(node) = identity(tape, op, parents) {
+        return node;
+    }
+
+    let is_constant = |node: &usize| matches!(tape.nodes[*node].op, Operation::Constant);
+    let folded = !parents.is_empty() && parents.iter().all(is_constant);
+    let (op, parents) = if folded {
+        (Operation::Constant, &[][..])
+    } else {
+        (op, parents)
+    };
+    // Every leaf and every constant created as one is a separate input that
+    // `update_value` can change. Folded constants are results and are shared
+    // like other operations.
+    if parents.is_empty() && !folded {
+        return tape.push(value, op, parents.iter().copied());
+    }
+
+    let mut key = Expression {
+        op,
+        parents: parents.to_vec(),
+        constant: match op {
+            Operation::Constant => value.to_f64().to_bits(),
+            _ => 0,
+        },
+    };
+    if let Operation::Add | Operation::Mul = op {
+        key.parents.sort_unstable();
+    }
+
+    // Leaves can change their value and whether they require grad after
+    // being used, so a match is only reused while it is still accurate.
+    let requires_grad = tape.requires_grad(op, parents);
+    if let Some(&node) = tape.expressions.get(&key) {
+        let existing = &tape.nodes[node];
+        if existing.value == value && existing.requires_grad == requires_grad {
+            return node;
+        }
+    }
+    let node = tape.push(value, op, parents.iter().copied());
+    tape.expressions.insert(key, node);
+    node
+}
+
+/// The operand `op` would return unchanged, if any.
+fn identity<F: Float>(tape: &Tape<F>, op: Operation, parents: &[usize]) -> Option<usize> {
+    let is = |node: usize, constant: F| {
+        let node = &tape.nodes[node];
+        matches!(node.op, Operation::Constant) && node.value == constant
+    };
+    match (op, parents) {
+        (Operation::Add, &[x, c]) | (Operation::Add, &[c, x]) if is(c, F::ZERO) => Some(x),
+        (Operation::Mul, &[x, c]) | (Operation::Mul, &[c, x]) if is(c, F::ONE) => Some(x),
+        (Operation::Div | Operation::Pow, &[x, c]) if is(c, F::ONE) => Some(x),
+        (Operation::Sum | Operation::Mean, &[x]) => Some(x),
+        _ => None,
+    }
+}
@@ -0,0 +1,140 @@
+use crate::anomaly;
+use crate::float::Float;
+use crate::tape::{self, Tape, with_tape};
+use crate::value::{Value, evaluate, propagate};
+
+/// A computation traced once and kept as a flat list of operations in
+/// topological order, which can be replayed forward and backward with new
+/// input values without recording anything on the tape.
+///
+/// The plan is exactly the graph built while tracing. Decisions made on
+/// values outside of operations, like which element softmax subtracts,
+/// stay as traced, operations that pick between operands (`max`, `clamp`,
+/// `select`, ...) decide again on every replay. Leaves and constants created
+/// by the traced function keep their values. Hooks registered inside it run
+/// on every `backward`.
+///
+/// ```
+/// # use grad::{Plan, Value};
+/// let mut plan = Plan::trace(|x| &(&x[0] * &x[1]) + &x[0].sin(), &[1.0, 2.0]);
+/// assert_eq!(plan.forward(&[0.0, 5.0]), 0.0);
+/// assert_eq!(plan.backward(), vec![6.0, 0.0]);
+/// ```
+pub struct Plan<F: Float = f64> {
+    tape: Tape<F>,
+    inputs: Vec<usize>,
+    output: usize,
+    // Only depends on the structure, so it is worked out once.
+    reachable: Vec<bool>,
+    contributions: Vec<(usize, F)>,
+}
+
+impl<F: Float> Plan<F> {
+    /// Records `f` applied to leaves holding `inputs`. Everything `f` uses
+    /// has to be created inside it, from its arguments or from scratch.
+    pub fn trace<G>(f: G, inputs: &[F]) -> Self
+    where
+        G: FnOnce(&[Value<F>]) -> Value<F>,
+    {
+        assert!(
+            tape::is_grad_enabled(),
+            "Cannot trace with graph construction turned off"
+        );
+
+        let _scope = tape::Scope::<F>::default();
+        let _plain = tape::Optimize::disabled();
+        let start = tape::len::<F>();
+        let leaves: Vec<Value<F>> = inputs.iter().map(|&x| Value::leaf(x)).collect();
+        let output = f(&leaves);
+        assert!(
+            output.0 >= start,
+            "The output has to be computed by the traced function"
+        );
+
+        let mut tape = with_tape::<F, _>(|tape| tape.split_off(start));
+        assert!(
+            !tape.has_checkpoints(),
+            "Cannot trace a function that uses checkpoints"
+        );
+        let output = output.0 - start;
+        tape.truncate(output + 1);
+
+        let mut reachable = vec![false; output + 1];
+        reachable[output] = tape.nodes[output].requires_grad;
+        for node in (0..=output).rev() {
+            if reachable[node] {
+                for &parent in tape.parents(node) {
+                    reachable[parent] |= tape.nodes[parent].requires_grad;
+                }
+            }
+        }
+
+        Plan {
+            tape,
+            inputs: leaves.iter().map(|leaf| leaf.0 - start).collect(),
+            output,
+            reachable,
+            contributions: Vec::new(),
+        }
+    }
+
+    /// Replays the computation on new input values and returns its output.
+    pub fn forward(&mut self, inputs: &[F]) -> F {
+        assert_eq!(inputs.len(), self.inputs.len(), "Input count mismatch");
+        for (&leaf, &value) in self.inputs.iter().zip(inputs) {
+            self.tape.nodes[leaf].value = value;
+        }
+
+        let detect_anomaly = tape::is_anomaly_detection_enabled();
+        for node in 0..self.tape.nodes.len() {
+            let value = evaluate(&self.tape, node);
+            self.tape.nodes[node].value = value;
+            if detect_anomaly && !value.is_finite() {
+                panic!("{}", anomaly::forward_report(&self.tape, node));
+            }
+        }
+        self.output()
+    }
+
+    /// Gradients of the output with respect to every input, at the values of
+    /// the last `forward`.
+    pub fn backward(&mut self) -> Vec<F> {
+        self.tape.zero_grads(0);
+        self.tape.nodes[self.output].grad = F::ONE;
+
+        let detect_anomaly = tape::is_anomaly_detection_enabled();
+        let has_hooks = self.tape.has_hooks();
+        for node in (0..=self.output).rev() {
+            if self.reachable[node] {
+                if has_hooks {
+                    self.run_hooks(node);
+                }
+                propagate(
+                    &mut self.tape,
+                    node,
+                    &mut self.reachable,
+                    &mut self.contributions,
+                    detect_anomaly,
+                );
+            }
+        }
+
+        self.inputs
+            .iter()
+            .map(|&input| self.tape.nodes[input].grad)
+            .collect()
+    }
+
+    /// Same as `hook::run_hooks`, for the hooks carried over from tracing.
+    fn run_hooks(&mut self, node: usize) {
+        let mut grad = self.tape.nodes[node].grad;
+        for hook in self.tape.hooks(node) {
+            grad = (hook.borrow_mut())(grad);
+        }
+        self.tape.nodes[node].grad = grad;
+    }
+
+    pub fn output(&self) -> F {
+        self.tape.nodes[self.output].value
+    }
+}
@@ -2,14 +2,14 @@ use crate::activation::Activation;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone)]
-pub struct ModelData {
+pub struct ModelData<F> {
     pub layer_sizes: Vec<usize>,
     pub activations: Vec<Activation>,
-    pub layers: Vec<Vec<NeuronData>>,
+    pub layers: Vec<Vec<NeuronData<F>>>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
-pub struct NeuronData {
-    pub weights: Vec<f64>,
-    pub bias: f64,
+pub struct NeuronData<F> {
+    pub weights: Vec<F>,
+    pub bias: F,
 }
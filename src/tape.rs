@@ -0,0 +1,519 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::anomaly;
+use crate::custom::CustomOp;
+use crate::float::Float;
+use crate::optimize::Expression;
+use crate::value::{Operation, Value};
+
+// Every Value is an index into the tape of the thread that created it.
+// Nodes are appended in evaluation order, so parents always have a lower
+// index than their children and the tape is already topologically sorted.
+
+pub(crate) struct Node<F> {
+    pub value: F,
+    pub grad: F,
+    pub op: Operation,
+    pub requires_grad: bool,
+    // The tape's generation when the node was recorded, see `Tape::index`.
+    generation: u32,
+    parent_start: u32,
+    parent_len: u32,
+}
+
+pub(crate) struct Tape<F> {
+    pub nodes: Vec<Node<F>>,
+    edges: Vec<usize>,
+    // Referenced by `Operation::Custom`, tagged with the node using them.
+    custom_ops: Vec<(usize, Arc<dyn CustomOp<F>>)>,
+    // Registered by `Value::register_hook`, in registration order.
+    hooks: Vec<(usize, Hook<F>)>,
+    // Nodes recorded with `Operation::Checkpoint`, in ascending order.
+    checkpoints: Vec<usize>,
+    // Nodes recorded with optimization on, by what they compute.
+    pub expressions: HashMap<Expression, usize>,
+    // Set by `Value::set_name`.
+    pub names: HashMap<usize, String>,
+    peak_nodes: usize,
+    peak_edges: usize,
+    // Bumped whenever nodes are removed, so the handles of removed nodes
+    // can be told apart from those of nodes recorded in their place.
+    generation: u32,
+}
+
+pub(crate) type Hook<F> = Rc<RefCell<dyn FnMut(F) -> F>>;
+
+impl<F: Float> Tape<F> {
+    fn new() -> Self {
+        Tape {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            custom_ops: Vec::new(),
+            hooks: Vec::new(),
+            checkpoints: Vec::new(),
+            expressions: HashMap::new(),
+            names: HashMap::new(),
+            peak_nodes: 0,
+            peak_edges: 0,
+            generation: 0,
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        value: F,
+        op: Operation,
+        parents: impl IntoIterator<Item = usize>,
+    ) -> usize {
+        let parent_start = self.edges.len();
+        self.edges.extend(parents);
+        let requires_grad = self.requires_grad(op, &self.edges[parent_start..]);
+        self.nodes.push(Node {
+            value,
+            grad: F::ZERO,
+            op,
+            requires_grad,
+            generation: self.generation,
+            parent_start: u32::try_from(parent_start).expect("Tape edge overflow"),
+            parent_len: u32::try_from(self.edges.len() - parent_start).expect("Tape edge overflow"),
+        });
+
+        let index = self.nodes.len() - 1;
+        if matches!(op, Operation::Checkpoint) {
+            self.checkpoints.push(index);
+        }
+        self.peak_nodes = self.peak_nodes.max(self.nodes.len());
+        self.peak_edges = self.peak_edges.max(self.edges.len());
+        if !value.is_finite() && is_anomaly_detection_enabled() {
+            panic!("{}", anomaly::forward_report(self, index));
+        }
+        index
+    }
+
+    /// Whether a node computing `op` from `parents` requires grad.
+    pub fn requires_grad(&self, op: Operation, parents: &[usize]) -> bool {
+        match op {
+            Operation::None => true,
+            Operation::Constant | Operation::Detach => false,
+            _ => parents
+                .iter()
+                .any(|&parent| self.nodes[parent].requires_grad),
+        }
+    }
+
+    pub fn push_custom(
+        &mut self,
+        value: F,
+        op: Arc<dyn CustomOp<F>>,
+        parents: impl IntoIterator<Item = usize>,
+    ) -> usize {
+        let slot = u32::try_from(self.custom_ops.len()).expect("Too many custom operations");
+        self.custom_ops.push((self.nodes.len(), op));
+        self.push(value, Operation::Custom(slot), parents)
+    }
+
+    pub fn custom_op(&self, slot: u32) -> &Arc<dyn CustomOp<F>> {
+        &self.custom_ops[slot as usize].1
+    }
+
+    /// Human readable name of the operation that produced `index`.
+    pub fn op_name(&self, index: usize) -> String {
+        match self.nodes[index].op {
+            Operation::Custom(slot) => self.custom_op(slot).name().to_string(),
+            Operation::None => "leaf".to_string(),
+            Operation::Constant => "constant".to_string(),
+            op => format!("{:?}", op),
+        }
+    }
+
+    pub fn add_hook(&mut self, index: usize, hook: Hook<F>) {
+        self.hooks.push((index, hook));
+    }
+
+    pub fn hooks(&self, index: usize) -> Vec<Hook<F>> {
+        self.hooks
+            .iter()
+            .filter(|(node, _)| *node == index)
+            .map(|(_, hook)| hook.clone())
+            .collect()
+    }
+
+    /// Nodes in `floor..=root` a backward pass has to let go of the tape
+    /// at, because they have hooks or are checkpoints. In ascending order.
+    pub fn stop_nodes(&self, floor: usize, root: usize) -> Vec<usize> {
+        let mut nodes: Vec<usize> = self
+            .hooks
+            .iter()
+            .map(|&(node, _)| node)
+            .chain(self.checkpoints.iter().copied())
+            .filter(|&node| node >= floor && node <= root)
+            .collect();
+        nodes.sort_unstable();
+        nodes.dedup();
+        nodes
+    }
+
+    pub fn has_hooks(&self) -> bool {
+        !self.hooks.is_empty()
+    }
+
+    pub fn has_checkpoints(&self) -> bool {
+        !self.checkpoints.is_empty()
+    }
+
+    /// Resets the gradient of every node from `start` on.
+    pub fn zero_grads(&mut self, start: usize) {
+        for node in &mut self.nodes[start..] {
+            node.grad = F::ZERO;
+        }
+    }
+
+    /// Moves the nodes from `start` on into a tape of their own, with
+    /// indices shifted down by `start`. Their hooks move along.
+    pub fn split_off(&mut self, start: usize) -> Tape<F> {
+        let edge_start = self.nodes[start].parent_start;
+        assert!(
+            self.edges[edge_start as usize..]
+                .iter()
+                .all(|&parent| parent >= start),
+            "Nodes from #{} on use nodes recorded before them",
+            start
+        );
+
+        // Custom operations are stored in creation order, so the ones moved
+        // are at the end and only their slots need shifting.
+        let first_slot = self.custom_ops.partition_point(|&(node, _)| node < start);
+        let custom_ops = self
+            .custom_ops
+            .split_off(first_slot)
+            .into_iter()
+            .map(|(node, op)| (node - start, op))
+            .collect();
+        let (hooks, kept) = std::mem::take(&mut self.hooks)
+            .into_iter()
+            .partition(|&(node, _)| node >= start);
+        self.hooks = kept;
+        let hooks = hooks
+            .into_iter()
+            .map(|(node, hook): (usize, Hook<F>)| (node - start, hook))
+            .collect();
+        self.expressions.retain(|_, node| *node < start);
+        self.names.retain(|&node, _| node < start);
+        let first_checkpoint = self.checkpoints.partition_point(|&node| node < start);
+        let checkpoints = self
+            .checkpoints
+            .split_off(first_checkpoint)
+            .into_iter()
+            .map(|node| node - start)
+            .collect();
+
+        self.generation = self.generation.wrapping_add(1);
+        let nodes = self
+            .nodes
+            .split_off(start)
+            .into_iter()
+            .map(|node| Node {
+                op: match node.op {
+                    Operation::Custom(slot) => Operation::Custom(slot - first_slot as u32),
+                    op => op,
+                },
+                parent_start: node.parent_start - edge_start,
+                ..node
+            })
+            .collect();
+        let edges = self
+            .edges
+            .split_off(edge_start as usize)
+            .into_iter()
+            .map(|parent| parent - start)
+            .collect();
+
+        Tape {
+            nodes,
+            edges,
+            custom_ops,
+            hooks,
+            checkpoints,
+            expressions: HashMap::new(),
+            names: HashMap::new(),
+            peak_nodes: 0,
+            peak_edges: 0,
+            generation: 0,
+        }
+    }
+
+    pub fn generation_of(&self, index: usize) -> u32 {
+        self.nodes[index].generation
+    }
+
+    /// Index of the node behind `value`, which has to be alive.
+    pub fn index(&self, value: &Value<F>) -> usize {
+        assert!(
+            self.nodes
+                .get(value.0)
+                .is_some_and(|node| node.generation == value.1),
+            "Value used after the tape was truncated past it, e.g. by dropping its Scope"
+        );
+        value.0
+    }
+
+    pub fn parents(&self, index: usize) -> &[usize] {
+        let node = &self.nodes[index];
+        let start = node.parent_start as usize;
+        &self.edges[start..start + node.parent_len as usize]
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.nodes.len() {
+            self.generation = self.generation.wrapping_add(1);
+            self.edges.truncate(self.nodes[len].parent_start as usize);
+            self.nodes.truncate(len);
+            while self.custom_ops.last().is_some_and(|&(node, _)| node >= len) {
+                self.custom_ops.pop();
+            }
+            self.hooks.retain(|&(node, _)| node < len);
+            while self.checkpoints.last().is_some_and(|&node| node >= len) {
+                self.checkpoints.pop();
+            }
+            if !self.expressions.is_empty() {
+                self.expressions.retain(|_, node| *node < len);
+            }
+            if !self.names.is_empty() {
+                self.names.retain(|&node, _| node < len);
+            }
+        }
+    }
+}
+
+thread_local! {
+    static TAPE_F32: RefCell<Tape<f32>> = RefCell::new(Tape::new());
+    static TAPE_F64: RefCell<Tape<f64>> = RefCell::new(Tape::new());
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+    static DETECT_ANOMALY: Cell<bool> = const { Cell::new(false) };
+    static OPTIMIZE: Cell<bool> = const { Cell::new(false) };
+}
+
+pub(crate) fn with_tape<F: Float, R>(f: impl FnOnce(&mut Tape<F>) -> R) -> R {
+    fn run<T: Float, F: Float, R>(tape: &RefCell<Tape<T>>, f: impl FnOnce(&mut Tape<F>) -> R) -> R {
+        let mut tape = tape.borrow_mut();
+        let tape = (&mut *tape as &mut dyn Any)
+            .downcast_mut::<Tape<F>>()
+            .expect("Float is only implemented for f32 and f64");
+        f(tape)
+    }
+
+    // `Float` is sealed, so one of the two always matches.
+    if (&F::ZERO as &dyn Any).is::<f32>() {
+        TAPE_F32.with(|tape| run(tape, f))
+    } else {
+        TAPE_F64.with(|tape| run(tape, f))
+    }
+}
+
+/// Number of nodes currently recorded on this thread's tape for `F`.
+pub fn len<F: Float>() -> usize {
+    with_tape::<F, _>(|tape| tape.nodes.len())
+}
+
+/// Size of this thread's tape for `F`, now and at its largest.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TapeStats {
+    pub nodes: usize,
+    pub edges: usize,
+    /// Most nodes recorded at once since the last `reset_peak`.
+    pub peak_nodes: usize,
+    /// Most parent references stored at once since the last `reset_peak`.
+    pub peak_edges: usize,
+}
+
+impl TapeStats {
+    /// Rough memory used by `peak_nodes` and `peak_edges`, in bytes.
+    pub fn peak_bytes<F: Float>(&self) -> usize {
+        self.peak_nodes * size_of::<Node<F>>() + self.peak_edges * size_of::<usize>()
+    }
+}
+
+pub fn stats<F: Float>() -> TapeStats {
+    with_tape::<F, _>(|tape| TapeStats {
+        nodes: tape.nodes.len(),
+        edges: tape.edges.len(),
+        peak_nodes: tape.peak_nodes,
+        peak_edges: tape.peak_edges,
+    })
+}
+
+/// Lowers the peaks reported by `stats` to the current size of the tape.
+pub fn reset_peak<F: Float>() {
+    with_tape::<F, _>(|tape| {
+        tape.peak_nodes = tape.nodes.len();
+        tape.peak_edges = tape.edges.len();
+    })
+}
+
+/// Discards every node recorded after the first `len` nodes.
+///
+/// Values created after that point must not be used afterwards.
+pub fn truncate<F: Float>(len: usize) {
+    with_tape::<F, _>(|tape| tape.truncate(len))
+}
+
+/// Truncates the tape back to its length at creation when dropped.
+///
+/// Parameters have to be created before the scope is opened, everything
+/// built inside it (forward pass, loss, gradients) is released at once.
+/// `Scope::new` covers the `f64` tape, use `Scope::<f32>::default()` for
+/// single precision.
+pub struct Scope<F: Float = f64> {
+    mark: usize,
+    _precision: PhantomData<F>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<F: Float> Default for Scope<F> {
+    fn default() -> Self {
+        Scope {
+            mark: len::<F>(),
+            _precision: PhantomData,
+        }
+    }
+}
+
+impl<F: Float> Drop for Scope<F> {
+    fn drop(&mut self) {
+        truncate::<F>(self.mark);
+    }
+}
+
+/// Whether operations on this thread currently record their inputs.
+pub fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(Cell::get)
+}
+
+/// Turns off graph construction on this thread until dropped.
+///
+/// Operations still compute their result, but it is recorded as a leaf
+/// without parents, so nothing can be backpropagated through it. Guards
+/// can be nested, dropping one restores the state it found.
+pub struct NoGrad {
+    previous: bool,
+}
+
+impl NoGrad {
+    pub fn new() -> Self {
+        NoGrad {
+            previous: GRAD_ENABLED.with(|enabled| enabled.replace(false)),
+        }
+    }
+}
+
+impl Default for NoGrad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for NoGrad {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|enabled| enabled.set(self.previous));
+    }
+}
+
+pub fn is_anomaly_detection_enabled() -> bool {
+    DETECT_ANOMALY.with(Cell::get)
+}
+
+/// Checks every value computed on this thread, and every gradient passed on
+/// by `backward`, until dropped.
+///
+/// The first value that is not finite panics with a report naming the
+/// operation that produced it, its operands and the path of nodes leading
+/// to it. Meant for debugging, `Model::train_parallel` turns it on in its
+/// worker threads when it is on in the calling one.
+pub struct DetectAnomaly {
+    previous: bool,
+}
+
+impl DetectAnomaly {
+    pub fn new() -> Self {
+        DetectAnomaly {
+            previous: DETECT_ANOMALY.with(|enabled| enabled.replace(true)),
+        }
+    }
+}
+
+impl Default for DetectAnomaly {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for DetectAnomaly {
+    fn drop(&mut self) {
+        DETECT_ANOMALY.with(|enabled| enabled.set(self.previous));
+    }
+}
+
+pub fn is_optimization_enabled() -> bool {
+    OPTIMIZE.with(Cell::get)
+}
+
+/// Simplifies the graph while it is built on this thread, until dropped.
+///
+/// Operations on constants only are folded into a constant, adding zero and
+/// multiplying, dividing or raising by one return the operand itself, and
+/// an operation already recorded on the same operands returns the earlier
+/// node instead of a copy of it. A value may then be shared by several
+/// expressions, hooks registered on it apply to all of them.
+///
+/// Values are identical to the ones computed without it. Gradients add up
+/// the same terms, but once a node is skipped or shared they reach its
+/// operands in a different order, so they can differ by rounding, usually
+/// in the last bit.
+///
+/// `Plan::trace` and checkpointed segments build their graphs without it.
+pub struct Optimize {
+    previous: bool,
+}
+
+impl Optimize {
+    pub fn new() -> Self {
+        Optimize::set(true)
+    }
+
+    pub(crate) fn disabled() -> Self {
+        Optimize::set(false)
+    }
+
+    fn set(enabled: bool) -> Self {
+        Optimize {
+            previous: OPTIMIZE.with(|optimize| optimize.replace(enabled)),
+        }
+    }
+}
+
+impl Default for Optimize {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Optimize {
+    fn drop(&mut self) {
+        OPTIMIZE.with(|optimize| optimize.set(self.previous));
+        // The nodes recorded so far can't be matched any more.
+        if !self.previous {
+            with_tape::<f32, _>(|tape| tape.expressions.clear());
+            with_tape::<f64, _>(|tape| tape.expressions.clear());
+        }
+    }
+}
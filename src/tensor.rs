@@ -0,0 +1,391 @@
+use std::ops;
+
+use crate::activation::Activation;
+use crate::value::Value;
+
+/// An n-dimensional, row-major array of `Value`s.
+///
+/// Every element is a regular node on the tape, so gradients of tensor
+/// expressions flow through the same autograd as scalar code. Elementwise
+/// operations broadcast like NumPy: shapes are aligned from the right and
+/// dimensions of size 1 are stretched. Tensors hold `f64` values only.
+#[derive(Clone, Debug)]
+pub struct Tensor {
+    data: Vec<Value>,
+    shape: Vec<usize>,
+}
+
+impl Tensor {
+    pub fn new(data: Vec<Value>, shape: &[usize]) -> Self {
+        assert_eq!(
+            data.len(),
+            shape.iter().product::<usize>(),
+            "Data length does not match shape {:?}",
+            shape
+        );
+        Tensor {
+            data,
+            shape: shape.to_vec(),
+        }
+    }
+
+    /// A tensor of fresh leaves holding `values`.
+    pub fn from_f64(values: &[f64], shape: &[usize]) -> Self {
+        Tensor::new(values.iter().map(|&x| Value::new(x)).collect(), shape)
+    }
+
+    /// A tensor of constant leaves holding `values`.
+    pub fn constant(values: &[f64], shape: &[usize]) -> Self {
+        Tensor::new(values.iter().map(|&x| Value::constant(x)).collect(), shape)
+    }
+
+    pub fn zeros(shape: &[usize]) -> Self {
+        Tensor::constant(&vec![0.0; shape.iter().product()], shape)
+    }
+
+    pub fn scalar(value: Value) -> Self {
+        Tensor::new(vec![value], &[])
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn data(&self) -> &[Value] {
+        &self.data
+    }
+
+    pub fn values(&self) -> Vec<f64> {
+        self.data.iter().map(Value::value).collect()
+    }
+
+    pub fn grads(&self) -> Vec<f64> {
+        self.data.iter().map(Value::grad).collect()
+    }
+
+    /// The single element of a tensor with one element.
+    pub fn item(&self) -> Value {
+        assert_eq!(self.len(), 1, "item() needs a tensor with one element");
+        self.data[0]
+    }
+
+    pub fn get(&self, index: &[usize]) -> Value {
+        assert_eq!(index.len(), self.ndim(), "Index rank mismatch");
+        let offset = index
+            .iter()
+            .zip(self.shape.iter().zip(strides(&self.shape)))
+            .map(|(&i, (&dim, stride))| {
+                assert!(
+                    i < dim,
+                    "Index {:?} out of bounds for {:?}",
+                    index,
+                    self.shape
+                );
+                i * stride
+            })
+            .sum::<usize>();
+        self.data[offset]
+    }
+
+    pub fn reshape(&self, shape: &[usize]) -> Tensor {
+        Tensor::new(self.data.clone(), shape)
+    }
+
+    /// Reorders the axes, `axes[i]` is the input axis that becomes axis `i`.
+    pub fn permute(&self, axes: &[usize]) -> Tensor {
+        assert_eq!(axes.len(), self.ndim(), "Permutation rank mismatch");
+        let mut seen = vec![false; axes.len()];
+        for &axis in axes {
+            assert!(
+                axis < axes.len() && !seen[axis],
+                "{:?} is not a permutation of the axes 0..{}",
+                axes,
+                axes.len()
+            );
+            seen[axis] = true;
+        }
+        let in_strides = strides(&self.shape);
+        let shape: Vec<usize> = axes.iter().map(|&axis| self.shape[axis]).collect();
+        let permuted_strides: Vec<usize> = axes.iter().map(|&axis| in_strides[axis]).collect();
+
+        let data = (0..self.len())
+            .map(|i| self.data[offset_of(i, &shape, &permuted_strides)])
+            .collect();
+        Tensor { data, shape }
+    }
+
+    /// Reverses the order of the axes, for a matrix this swaps rows and columns.
+    pub fn transpose(&self) -> Tensor {
+        let axes: Vec<usize> = (0..self.ndim()).rev().collect();
+        self.permute(&axes)
+    }
+
+    pub fn map(&self, f: impl Fn(&Value) -> Value) -> Tensor {
+        Tensor {
+            data: self.data.iter().map(f).collect(),
+            shape: self.shape.clone(),
+        }
+    }
+
+    /// Combines two tensors elementwise after broadcasting them to a common shape.
+    pub fn zip_with(&self, other: &Tensor, f: impl Fn(&Value, &Value) -> Value) -> Tensor {
+        let shape = broadcast_shape(&self.shape, &other.shape);
+        let self_strides = broadcast_strides(&self.shape, &shape);
+        let other_strides = broadcast_strides(&other.shape, &shape);
+
+        let data = (0..shape.iter().product())
+            .map(|i| {
+                f(
+                    &self.data[offset_of(i, &shape, &self_strides)],
+                    &other.data[offset_of(i, &shape, &other_strides)],
+                )
+            })
+            .collect();
+        Tensor { data, shape }
+    }
+
+    pub fn exp(&self) -> Tensor {
+        self.map(Value::exp)
+    }
+
+    pub fn ln(&self) -> Tensor {
+        self.map(Value::ln)
+    }
+
+    pub fn tanh(&self) -> Tensor {
+        self.map(Value::tanh)
+    }
+
+    pub fn sigmoid(&self) -> Tensor {
+        self.map(Value::sigmoid)
+    }
+
+    /// Applies `activation` to every vector along the last axis.
+    pub fn activation(&self, activation: Activation) -> Tensor {
+        let width = self.shape.last().copied().unwrap_or(1);
+        let data = self
+            .data
+            .chunks(width.max(1))
+            .flat_map(|row| activation.apply(row))
+            .collect();
+        Tensor {
+            data,
+            shape: self.shape.clone(),
+        }
+    }
+
+    /// Matrix product of two 2-D tensors.
+    pub fn matmul(&self, other: &Tensor) -> Tensor {
+        assert!(
+            self.ndim() == 2 && other.ndim() == 2,
+            "matmul needs two matrices, got {:?} and {:?}",
+            self.shape,
+            other.shape
+        );
+        let (m, k, n) = (self.shape[0], self.shape[1], other.shape[1]);
+        assert_eq!(k, other.shape[0], "Inner dimensions do not match");
+
+        let mut data = Vec::with_capacity(m * n);
+        for i in 0..m {
+            let row = &self.data[i * k..(i + 1) * k];
+            for j in 0..n {
+                let column: Vec<Value> = (0..k).map(|l| other.data[l * n + j]).collect();
+                data.push(Value::dot(row, &column));
+            }
+        }
+        Tensor {
+            data,
+            shape: vec![m, n],
+        }
+    }
+
+    /// Sum of all elements.
+    pub fn sum(&self) -> Value {
+        Value::sum(&self.data)
+    }
+
+    pub fn mean(&self) -> Value {
+        Value::mean(&self.data)
+    }
+
+    /// Sums over `axis`, which is removed from the shape.
+    pub fn sum_axis(&self, axis: usize) -> Tensor {
+        self.reduce_axis(axis, Value::sum)
+    }
+
+    /// Averages over `axis`, which is removed from the shape.
+    pub fn mean_axis(&self, axis: usize) -> Tensor {
+        self.reduce_axis(axis, Value::mean)
+    }
+
+    fn reduce_axis(&self, axis: usize, reduce: impl Fn(&[Value]) -> Value) -> Tensor {
+        assert!(axis < self.ndim(), "Axis {} out of range", axis);
+        let outer: usize = self.shape[..axis].iter().product();
+        let size = self.shape[axis];
+        let inner: usize = self.shape[axis + 1..].iter().product();
+
+        let mut data = Vec::with_capacity(outer * inner);
+        let mut values = Vec::with_capacity(size);
+        for o in 0..outer {
+            for i in 0..inner {
+                values.clear();
+                values.extend((0..size).map(|s| self.data[(o * size + s) * inner + i]));
+                data.push(reduce(&values));
+            }
+        }
+
+        let mut shape = self.shape.clone();
+        shape.remove(axis);
+        Tensor { data, shape }
+    }
+}
+
+fn strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1];
+    }
+    strides
+}
+
+fn broadcast_shape(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let ndim = a.len().max(b.len());
+    (0..ndim)
+        .map(|i| {
+            let a_dim = if i < ndim - a.len() {
+                1
+            } else {
+                a[i - (ndim - a.len())]
+            };
+            let b_dim = if i < ndim - b.len() {
+                1
+            } else {
+                b[i - (ndim - b.len())]
+            };
+            match (a_dim, b_dim) {
+                (x, y) if x == y => x,
+                (1, y) => y,
+                (x, 1) => x,
+                _ => panic!("Shapes {:?} and {:?} cannot be broadcast", a, b),
+            }
+        })
+        .collect()
+}
+
+/// Strides for reading a tensor of `shape` as if it had `target` shape,
+/// stretched dimensions get a stride of zero.
+fn broadcast_strides(shape: &[usize], target: &[usize]) -> Vec<usize> {
+    let own = strides(shape);
+    let missing = target.len() - shape.len();
+    (0..target.len())
+        .map(|i| {
+            if i < missing || shape[i - missing] == 1 {
+                0
+            } else {
+                own[i - missing]
+            }
+        })
+        .collect()
+}
+
+/// Position in the data of the `index`-th element of `shape`, read with `strides`.
+fn offset_of(mut index: usize, shape: &[usize], strides: &[usize]) -> usize {
+    let mut offset = 0;
+    for (&dim, &stride) in shape.iter().zip(strides).rev() {
+        offset += (index % dim) * stride;
+        index /= dim;
+    }
+    offset
+}
+
+impl ops::Add for &Tensor {
+    type Output = Tensor;
+
+    fn add(self, other: &Tensor) -> Tensor {
+        self.zip_with(other, |a, b| a + b)
+    }
+}
+
+impl ops::Add<f64> for &Tensor {
+    type Output = Tensor;
+
+    fn add(self, other: f64) -> Tensor {
+        self.map(|a| a + other)
+    }
+}
+
+impl ops::Sub for &Tensor {
+    type Output = Tensor;
+
+    fn sub(self, other: &Tensor) -> Tensor {
+        self.zip_with(other, |a, b| a - b)
+    }
+}
+
+impl ops::Sub<f64> for &Tensor {
+    type Output = Tensor;
+
+    fn sub(self, other: f64) -> Tensor {
+        self.map(|a| a - other)
+    }
+}
+
+impl ops::Mul for &Tensor {
+    type Output = Tensor;
+
+    fn mul(self, other: &Tensor) -> Tensor {
+        self.zip_with(other, |a, b| a * b)
+    }
+}
+
+impl ops::Mul<f64> for &Tensor {
+    type Output = Tensor;
+
+    fn mul(self, other: f64) -> Tensor {
+        self.map(|a| a * other)
+    }
+}
+
+impl ops::Div for &Tensor {
+    type Output = Tensor;
+
+    fn div(self, other: &Tensor) -> Tensor {
+        self.zip_with(other, |a, b| a / b)
+    }
+}
+
+impl ops::Div<f64> for &Tensor {
+    type Output = Tensor;
+
+    fn div(self, other: f64) -> Tensor {
+        self.map(|a| a / other)
+    }
+}
+
+impl ops::BitXor<f64> for &Tensor {
+    type Output = Tensor;
+
+    fn bitxor(self, other: f64) -> Tensor {
+        self.map(|a| a ^ other)
+    }
+}
+
+impl ops::Neg for &Tensor {
+    type Output = Tensor;
+
+    fn neg(self) -> Tensor {
+        self.map(|a| -a)
+    }
+}
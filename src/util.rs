@@ -16,7 +16,7 @@ pub fn take_subset<T: Clone>(data: &[T], start: usize, count: usize) -> Vec<T> {
     data[start..end].to_vec()
 }
 
-pub fn to_label(data: Vec<f64>) -> u8 {
+pub fn to_label<T: PartialOrd>(data: Vec<T>) -> u8 {
     data.iter()
         .position(|x| {
             x == data
@@ -1,168 +1,705 @@
-use std::{cell::RefCell, collections::HashSet, sync::Arc};
+use std::marker::PhantomData;
 
-#[derive(Debug)]
+use crate::anomaly;
+use crate::float::Float;
+use crate::hook::run_hooks;
+use crate::optimize;
+use crate::tape::{
+    Tape, is_anomaly_detection_enabled, is_grad_enabled, is_optimization_enabled, with_tape,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Operation {
     Add,
     Mul,
     Div,
     Pow,
     Log,
+    Exp,
+    Tanh,
+    Sqrt,
+    Sigmoid,
+    Sin,
+    Cos,
+    Tan,
+    Atan2,
+    Sinh,
+    Cosh,
+    Max,
+    Min,
+    Abs,
+    Clamp,
+    Select,
+    Sum,
+    Mean,
+    Dot,
+    Custom(u32),
+    // Stands in for a segment discarded by `Checkpoints::segment`. It has no
+    // parents, the values the segment read are found again when it reruns.
+    Checkpoint,
+    // One output of a discarded segment, its only parent is the checkpoint.
+    CheckpointOutput,
+    Detach,
+    Constant,
     None,
 }
 
-#[derive(Debug)]
-pub struct ValueData {
-    pub value: RefCell<f64>,
-    pub grad: RefCell<f64>,
-    pub result_of: Operation,
-    pub parents: Vec<Arc<ValueData>>,
+/// A scalar recorded on the tape of the current thread, `f64` unless a
+/// different precision is chosen.
+///
+/// `Value::new`, `Value::constant` and `Value::untracked` create `f64`
+/// values, so literals need no annotation. `Value::leaf`,
+/// `Value::constant_leaf` and `Value::untracked_leaf` do the same for any
+/// precision, e.g. `Value::<f32>::leaf(1.0)`. `Tensor` and `Dual` are `f64`
+/// only.
+///
+/// A value can only be used on the thread that created it, and only until
+/// the tape is truncated past it. Using it after that panics.
+///
+/// ```compile_fail
+/// fn send<T: Send>(_: T) {}
+/// send(grad::Value::new(1.0));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Value<F: Float = f64>(
+    pub(crate) usize,
+    pub(crate) u32,
+    PhantomData<(F, *const ())>,
+);
+
+impl Value {
+    pub fn new(value: f64) -> Self {
+        Value::leaf(value)
+    }
+
+    /// A leaf for a literal operand, such as the `2.0` in `&x * 2.0`.
+    pub fn constant(value: f64) -> Self {
+        Value::constant_leaf(value)
+    }
+
+    /// A leaf that does not require grad, for data such as inputs and targets.
+    pub fn untracked(value: f64) -> Self {
+        Value::untracked_leaf(value)
+    }
 }
 
-#[derive(Clone, Debug)]
-pub struct Value(pub Arc<ValueData>);
+impl<F: Float> Value<F> {
+    pub(crate) fn at(tape: &Tape<F>, index: usize) -> Self {
+        Value(index, tape.generation_of(index), PhantomData)
+    }
 
-impl ValueData {
-    pub fn new(value: f64, result_of: Operation, parents: Vec<Arc<ValueData>>) -> Self {
-        ValueData {
-            value: RefCell::new(value),
-            grad: RefCell::new(0.0),
-            result_of,
-            parents,
+    /// Index of this value on the tape, checked to still be alive.
+    pub(crate) fn index(&self) -> usize {
+        with_tape::<F, _>(|tape| tape.index(self))
+    }
+
+    /// `Value::new` for any precision.
+    pub fn leaf(value: F) -> Self {
+        Value::from_op(value, Operation::None, &[])
+    }
+
+    /// A leaf for a literal operand, such as the `2.0` in `&x * 2.0`.
+    pub fn constant_leaf(value: F) -> Self {
+        Value::from_op(value, Operation::Constant, &[])
+    }
+
+    /// A leaf that does not require grad, for data such as inputs and targets.
+    pub fn untracked_leaf(value: F) -> Self {
+        let leaf = Value::leaf(value);
+        leaf.set_requires_grad(false);
+        leaf
+    }
+
+    pub(crate) fn from_op(value: F, result_of: Operation, parents: &[&Value<F>]) -> Self {
+        if !is_grad_enabled() && !parents.is_empty() {
+            return Value::untracked_leaf(value);
         }
+        with_tape::<F, _>(|tape| {
+            for parent in parents {
+                tape.index(parent);
+            }
+            let parents = parents.iter().map(|p| p.0);
+            let index = if is_optimization_enabled() {
+                optimize::push(tape, value, result_of, &parents.collect::<Vec<_>>())
+            } else {
+                tape.push(value, result_of, parents)
+            };
+            Value::at(tape, index)
+        })
     }
-}
 
-impl Value {
-    pub fn new(value: f64) -> Self {
-        Value(Arc::new(ValueData::new(value, Operation::None, Vec::new())))
+    pub fn value(&self) -> F {
+        with_tape::<F, _>(|tape| tape.nodes[tape.index(self)].value)
+    }
+
+    pub fn update_value(&self, new_value: F) {
+        with_tape::<F, _>(|tape| {
+            let index = tape.index(self);
+            tape.nodes[index].value = new_value
+        })
     }
 
-    pub fn value(&self) -> f64 {
-        *self.0.value.borrow()
+    pub fn grad(&self) -> F {
+        with_tape::<F, _>(|tape| tape.nodes[tape.index(self)].grad)
     }
 
-    pub fn update_value(&self, new_value: f64) {
-        *self.0.value.borrow_mut() = new_value;
+    /// Whether gradients flow into this value. Leaves from `new` require
+    /// grad, constants and untracked leaves don't, and every other value
+    /// requires grad when one of its inputs does. `backward` skips the
+    /// parts of the graph that don't.
+    pub fn requires_grad(&self) -> bool {
+        with_tape::<F, _>(|tape| tape.nodes[tape.index(self)].requires_grad)
     }
 
-    pub fn grad(&self) -> f64 {
-        *self.0.grad.borrow()
+    /// Changes whether a leaf requires grad. Values computed from it
+    /// afterwards pick up the new setting.
+    pub fn set_requires_grad(&self, requires_grad: bool) {
+        with_tape::<F, _>(|tape| {
+            let index = tape.index(self);
+            assert!(
+                tape.parents(index).is_empty(),
+                "requires_grad can only be set on leaves"
+            );
+            tape.nodes[index].requires_grad = requires_grad;
+        })
     }
 
-    pub fn ln(&self) -> Value {
+    /// A copy of this value that gradients do not flow through, so the
+    /// expression behind it is treated as a constant by `backward`.
+    pub fn detach(&self) -> Value<F> {
+        Value::from_op(self.value(), Operation::Detach, &[self])
+    }
+
+    pub fn ln(&self) -> Value<F> {
         let result = self.value().ln();
-        Value(Arc::new(ValueData::new(
+        Value::from_op(result, Operation::Log, &[self])
+    }
+
+    pub fn exp(&self) -> Value<F> {
+        let result = self.value().exp();
+        Value::from_op(result, Operation::Exp, &[self])
+    }
+
+    pub fn tanh(&self) -> Value<F> {
+        let result = self.value().tanh();
+        Value::from_op(result, Operation::Tanh, &[self])
+    }
+
+    pub fn sqrt(&self) -> Value<F> {
+        let result = self.value().sqrt();
+        Value::from_op(result, Operation::Sqrt, &[self])
+    }
+
+    pub fn sigmoid(&self) -> Value<F> {
+        let result = sigmoid(self.value());
+        Value::from_op(result, Operation::Sigmoid, &[self])
+    }
+
+    pub fn sin(&self) -> Value<F> {
+        let result = self.value().sin();
+        Value::from_op(result, Operation::Sin, &[self])
+    }
+
+    pub fn cos(&self) -> Value<F> {
+        let result = self.value().cos();
+        Value::from_op(result, Operation::Cos, &[self])
+    }
+
+    pub fn tan(&self) -> Value<F> {
+        let result = self.value().tan();
+        Value::from_op(result, Operation::Tan, &[self])
+    }
+
+    /// Four-quadrant arctangent of `self / other`, like `f64::atan2`.
+    pub fn atan2(&self, other: &Value<F>) -> Value<F> {
+        let result = self.value().atan2(other.value());
+        Value::from_op(result, Operation::Atan2, &[self, other])
+    }
+
+    pub fn sinh(&self) -> Value<F> {
+        let result = self.value().sinh();
+        Value::from_op(result, Operation::Sinh, &[self])
+    }
+
+    pub fn cosh(&self) -> Value<F> {
+        let result = self.value().cosh();
+        Value::from_op(result, Operation::Cosh, &[self])
+    }
+
+    pub fn max(&self, other: &Value<F>) -> Value<F> {
+        let result = self.value().max(other.value());
+        Value::from_op(result, Operation::Max, &[self, other])
+    }
+
+    pub fn min(&self, other: &Value<F>) -> Value<F> {
+        let result = self.value().min(other.value());
+        Value::from_op(result, Operation::Min, &[self, other])
+    }
+
+    pub fn abs(&self) -> Value<F> {
+        let result = self.value().abs();
+        Value::from_op(result, Operation::Abs, &[self])
+    }
+
+    /// Limits the value to `[min, max]`. The gradient only flows back to
+    /// `self` while it lies inside the interval.
+    pub fn clamp(&self, min: F, max: F) -> Value<F> {
+        let result = self.value().clamp(min, max);
+        Value::from_op(
             result,
-            Operation::Log,
-            vec![Arc::clone(&self.0)],
-        )))
+            Operation::Clamp,
+            &[self, &Value::constant_leaf(min), &Value::constant_leaf(max)],
+        )
+    }
+
+    /// Picks `a` where `cond` is positive and `b` otherwise. Only the picked
+    /// branch receives gradient, `cond` never does.
+    pub fn select(cond: &Value<F>, a: &Value<F>, b: &Value<F>) -> Value<F> {
+        let result = if cond.value() > F::ZERO {
+            a.value()
+        } else {
+            b.value()
+        };
+        Value::from_op(result, Operation::Select, &[cond, a, b])
+    }
+
+    /// Sum of `values` as a single node, zero when empty.
+    pub fn sum(values: &[Value<F>]) -> Value<F> {
+        let result = values.iter().map(Value::value).sum();
+        let parents: Vec<&Value<F>> = values.iter().collect();
+        Value::from_op(result, Operation::Sum, &parents)
+    }
+
+    /// Mean of `values` as a single node.
+    pub fn mean(values: &[Value<F>]) -> Value<F> {
+        assert!(!values.is_empty(), "Mean of no values");
+        let count = F::from_f64(values.len() as f64);
+        let result = values.iter().map(Value::value).sum::<F>() / count;
+        let parents: Vec<&Value<F>> = values.iter().collect();
+        Value::from_op(result, Operation::Mean, &parents)
+    }
+
+    /// Inner product of `a` and `b` as a single node.
+    pub fn dot(a: &[Value<F>], b: &[Value<F>]) -> Value<F> {
+        assert_eq!(a.len(), b.len(), "Dot product of different lengths");
+        let result = a.iter().zip(b).map(|(x, y)| x.value() * y.value()).sum();
+        let parents: Vec<&Value<F>> = a.iter().chain(b).collect();
+        Value::from_op(result, Operation::Dot, &parents)
     }
 
     pub fn backward(&self) {
-        *self.0.grad.borrow_mut() = 1.0;
-
-        let mut topo = Vec::new();
-        let mut visited = HashSet::new();
-
-        fn build_topo(
-            node: &Arc<ValueData>,
-            visited: &mut HashSet<usize>,
-            topo: &mut Vec<Arc<ValueData>>,
-        ) {
-            let node_id = Arc::as_ptr(node) as usize;
-            if !visited.contains(&node_id) {
-                visited.insert(node_id);
-                for parent in node.parents.iter() {
-                    build_topo(parent, visited, topo);
+        self.backward_with(F::ONE);
+    }
+
+    /// Like `backward`, with `seed` as the gradient of this value instead of
+    /// one, which gives the vector-Jacobian product for that seed.
+    pub fn backward_with(&self, seed: F) {
+        backward_from::<F>(&[(self.index(), seed)], 0, &mut no_checkpoints);
+    }
+
+    pub fn zero_grad(&self) {
+        with_tape::<F, _>(|tape| {
+            let index = tape.index(self);
+            tape.nodes[index].grad = F::ZERO
+        })
+    }
+}
+
+pub(crate) fn no_checkpoints(_: usize) -> Vec<usize> {
+    panic!("Checkpointed segments can only be differentiated with Checkpoints::backward");
+}
+
+/// Backpropagates from every `(node, seed)` root in a single pass. The roots
+/// start from their seeds, repeated roots add up. Only nodes from `floor` on
+/// are visited, the gradients of those below are accumulated but not passed
+/// on. Returns which nodes were reached.
+///
+/// `on_checkpoint` is called with every reachable checkpoint node once the
+/// gradients of its outputs are complete, and returns the nodes below it the
+/// segment passed gradients to.
+pub(crate) fn backward_from<F: Float>(
+    roots: &[(usize, F)],
+    floor: usize,
+    on_checkpoint: &mut dyn FnMut(usize) -> Vec<usize>,
+) -> Vec<bool> {
+    let Some(root) = roots.iter().map(|&(node, _)| node).max() else {
+        return Vec::new();
+    };
+    let detect_anomaly = is_anomaly_detection_enabled();
+    let mut reachable = vec![false; root + 1];
+    let mut stops = with_tape::<F, _>(|tape| {
+        for &(node, _) in roots {
+            tape.nodes[node].grad = F::ZERO;
+        }
+        for &(node, seed) in roots {
+            tape.nodes[node].grad += seed;
+            reachable[node] = tape.nodes[node].requires_grad;
+        }
+        tape.stop_nodes(floor, root)
+    });
+    let mut contributions = Vec::new();
+
+    // Parents always sit below their children on the tape, so walking it
+    // backwards from the roots visits nodes in reverse topological order.
+    // Only nodes reachable from a root take part. Hooks and checkpoints may
+    // use the tape themselves, so it is let go of while they run.
+    let mut end = root + 1;
+    loop {
+        let start = stops.last().map_or(floor, |&node| node + 1);
+        with_tape::<F, _>(|tape| {
+            for node in (start..end).rev() {
+                if reachable[node] {
+                    propagate(
+                        tape,
+                        node,
+                        &mut reachable,
+                        &mut contributions,
+                        detect_anomaly,
+                    );
                 }
-                topo.push(Arc::clone(node));
             }
+        });
+
+        let Some(node) = stops.pop() else {
+            break;
+        };
+        if reachable[node] {
+            let op = with_tape::<F, _>(|tape| tape.nodes[node].op);
+            if let Operation::Checkpoint = op {
+                for input in on_checkpoint(node) {
+                    reachable[input] = true;
+                }
+            } else {
+                run_hooks::<F>(node);
+            }
+        }
+        end = node + 1;
+    }
+    reachable
+}
+
+/// Adds the gradient of `node` to the parents that require grad and marks
+/// them as reachable.
+pub(crate) fn propagate<F: Float>(
+    tape: &mut Tape<F>,
+    node: usize,
+    reachable: &mut [bool],
+    contributions: &mut Vec<(usize, F)>,
+    detect_anomaly: bool,
+) {
+    contributions.clear();
+    let node_grad = tape.nodes[node].grad;
+    local_gradients(tape, node, node_grad, contributions);
+    if detect_anomaly {
+        check_gradients(tape, node, node_grad, contributions, reachable);
+    }
+    for &(parent, grad) in contributions.iter() {
+        if tape.nodes[parent].requires_grad {
+            tape.nodes[parent].grad += grad;
         }
+    }
+
+    for &parent in tape.parents(node) {
+        reachable[parent] |= tape.nodes[parent].requires_grad;
+    }
+}
+
+/// Panics with an anomaly report on the first non-finite contribution.
+pub(crate) fn check_gradients<F: Float>(
+    tape: &Tape<F>,
+    node: usize,
+    node_grad: F,
+    contributions: &[(usize, F)],
+    reachable: &[bool],
+) {
+    if let Some(&(parent, grad)) = contributions.iter().find(|(_, grad)| !grad.is_finite()) {
+        panic!(
+            "{}",
+            anomaly::backward_report(tape, node, node_grad, parent, grad, reachable)
+        );
+    }
+}
+
+fn sigmoid<F: Float>(x: F) -> F {
+    // Only ever exponentiate non-positive numbers to avoid overflow.
+    if x >= F::ZERO {
+        F::ONE / (F::ONE + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (F::ONE + e)
+    }
+}
+
+/// Recomputes the value of `node` from the current values of its parents,
+/// the same way the operation computed it when it was recorded. Leaves keep
+/// their value.
+pub(crate) fn evaluate<F: Float>(tape: &Tape<F>, node: usize) -> F {
+    let parents = tape.parents(node);
+    let operand = |i: usize| tape.nodes[parents[i]].value;
 
-        build_topo(&self.0, &mut visited, &mut topo);
+    match tape.nodes[node].op {
+        Operation::Add => operand(0) + operand(1),
+        Operation::Mul => operand(0) * operand(1),
+        Operation::Div => operand(0) / operand(1),
+        Operation::Pow => operand(0).powf(operand(1)),
+        Operation::Log => operand(0).ln(),
+        Operation::Exp => operand(0).exp(),
+        Operation::Tanh => operand(0).tanh(),
+        Operation::Sqrt => operand(0).sqrt(),
+        Operation::Sigmoid => sigmoid(operand(0)),
+        Operation::Sin => operand(0).sin(),
+        Operation::Cos => operand(0).cos(),
+        Operation::Tan => operand(0).tan(),
+        Operation::Atan2 => operand(0).atan2(operand(1)),
+        Operation::Sinh => operand(0).sinh(),
+        Operation::Cosh => operand(0).cosh(),
+        Operation::Max => operand(0).max(operand(1)),
+        Operation::Min => operand(0).min(operand(1)),
+        Operation::Abs => operand(0).abs(),
+        Operation::Clamp => operand(0).clamp(operand(1), operand(2)),
+        Operation::Select => {
+            if operand(0) > F::ZERO {
+                operand(1)
+            } else {
+                operand(2)
+            }
+        }
+        Operation::Sum => (0..parents.len()).map(operand).sum(),
+        Operation::Mean => {
+            (0..parents.len()).map(operand).sum::<F>() / F::from_f64(parents.len() as f64)
+        }
+        Operation::Dot => {
+            let half = parents.len() / 2;
+            (0..half).map(|i| operand(i) * operand(half + i)).sum()
+        }
+        Operation::Custom(slot) => {
+            let inputs: Vec<F> = (0..parents.len()).map(operand).collect();
+            tape.custom_op(slot).forward(&inputs)
+        }
+        Operation::Checkpoint | Operation::CheckpointOutput => {
+            panic!("Checkpointed segments cannot be replayed")
+        }
+        Operation::Detach => operand(0),
+        Operation::Constant | Operation::None => tape.nodes[node].value,
+    }
+}
 
-        for node in topo.iter().rev() {
-            let grad = *node.grad.borrow();
+/// Pushes the gradient `grad` of `node` onto its parents, as
+/// `(parent, contribution)` pairs, following the node's operation.
+pub(crate) fn local_gradients<F: Float>(
+    tape: &Tape<F>,
+    node: usize,
+    grad: F,
+    out: &mut Vec<(usize, F)>,
+) {
+    let value = tape.nodes[node].value;
+    let parents = tape.parents(node);
 
-            match node.result_of {
-                Operation::Add => {
-                    if node.parents.len() == 2 {
-                        let a = &node.parents[0];
-                        let b = &node.parents[1];
-                        *a.grad.borrow_mut() += grad;
-                        *b.grad.borrow_mut() += grad;
-                    }
+    match tape.nodes[node].op {
+        Operation::Add => {
+            if let &[a, b] = parents {
+                out.push((a, grad));
+                out.push((b, grad));
+            }
+        }
+        Operation::Mul => {
+            if let &[a, b] = parents {
+                let (a_value, b_value) = (tape.nodes[a].value, tape.nodes[b].value);
+                out.push((a, grad * b_value));
+                out.push((b, grad * a_value));
+            }
+        }
+        Operation::Div => {
+            if let &[a, b] = parents {
+                let (a_value, b_value) = (tape.nodes[a].value, tape.nodes[b].value);
+                if b_value != F::ZERO {
+                    out.push((a, grad / b_value));
+                    out.push((b, -grad * a_value / (b_value * b_value)));
                 }
-                Operation::Mul => {
-                    if node.parents.len() == 2 {
-                        let a = &node.parents[0];
-                        let b = &node.parents[1];
-                        *a.grad.borrow_mut() += grad * *b.value.borrow();
-                        *b.grad.borrow_mut() += grad * *a.value.borrow();
-                    }
+            }
+        }
+        Operation::Pow => {
+            if let &[a, b] = parents {
+                let (a_value, b_value) = (tape.nodes[a].value, tape.nodes[b].value);
+                out.push((a, grad * b_value * a_value.powf(b_value - F::ONE)));
+                if a_value > F::ZERO {
+                    out.push((b, grad * a_value.powf(b_value) * a_value.ln()));
                 }
-                Operation::Div => {
-                    if node.parents.len() == 2 {
-                        let a = &node.parents[0];
-                        let b = &node.parents[1];
-                        if *b.value.borrow() != 0.0 {
-                            *a.grad.borrow_mut() += grad / *b.value.borrow();
-                            *b.grad.borrow_mut() +=
-                                -grad * *a.value.borrow() / (*b.value.borrow() * *b.value.borrow());
-                        }
-                    }
+            }
+        }
+        Operation::Log => {
+            if let &[a] = parents {
+                let a_value = tape.nodes[a].value;
+                if a_value > F::ZERO {
+                    out.push((a, grad / a_value));
                 }
-                Operation::Pow => {
-                    if node.parents.len() == 2 {
-                        let a = &node.parents[0];
-                        let b = &node.parents[1];
-                        *a.grad.borrow_mut() += grad
-                            * *b.value.borrow()
-                            * (*a.value.borrow()).powf(*b.value.borrow() - 1.0);
-                        if *a.value.borrow() > 0.0 {
-                            *b.grad.borrow_mut() += grad
-                                * (*a.value.borrow()).powf(*b.value.borrow())
-                                * (*a.value.borrow()).ln();
-                        }
-                    }
+            }
+        }
+        Operation::Exp => {
+            if let &[a] = parents {
+                out.push((a, grad * value));
+            }
+        }
+        Operation::Tanh => {
+            if let &[a] = parents {
+                out.push((a, grad * (F::ONE - value * value)));
+            }
+        }
+        Operation::Sqrt => {
+            if let &[a] = parents
+                && value > F::ZERO
+            {
+                out.push((a, grad / (value + value)));
+            }
+        }
+        Operation::Sigmoid => {
+            if let &[a] = parents {
+                out.push((a, grad * value * (F::ONE - value)));
+            }
+        }
+        Operation::Sin => {
+            if let &[a] = parents {
+                out.push((a, grad * tape.nodes[a].value.cos()));
+            }
+        }
+        Operation::Cos => {
+            if let &[a] = parents {
+                out.push((a, -grad * tape.nodes[a].value.sin()));
+            }
+        }
+        Operation::Tan => {
+            if let &[a] = parents {
+                out.push((a, grad * (F::ONE + value * value)));
+            }
+        }
+        Operation::Atan2 => {
+            if let &[y, x] = parents {
+                let (y_value, x_value) = (tape.nodes[y].value, tape.nodes[x].value);
+                let r2 = x_value * x_value + y_value * y_value;
+                if r2 != F::ZERO {
+                    out.push((y, grad * x_value / r2));
+                    out.push((x, -grad * y_value / r2));
                 }
-                Operation::Log => {
-                    if node.parents.len() == 1 {
-                        let a = &node.parents[0];
-                        if *a.value.borrow() > 0.0 {
-                            *a.grad.borrow_mut() += grad / *a.value.borrow();
-                        }
-                    }
+            }
+        }
+        Operation::Sinh => {
+            if let &[a] = parents {
+                out.push((a, grad * tape.nodes[a].value.cosh()));
+            }
+        }
+        Operation::Cosh => {
+            if let &[a] = parents {
+                out.push((a, grad * tape.nodes[a].value.sinh()));
+            }
+        }
+        Operation::Max => {
+            if let &[a, b] = parents {
+                let picked = if tape.nodes[a].value >= tape.nodes[b].value {
+                    a
+                } else {
+                    b
+                };
+                out.push((picked, grad));
+            }
+        }
+        Operation::Min => {
+            if let &[a, b] = parents {
+                let picked = if tape.nodes[a].value <= tape.nodes[b].value {
+                    a
+                } else {
+                    b
+                };
+                out.push((picked, grad));
+            }
+        }
+        Operation::Abs => {
+            if let &[a] = parents {
+                let a_value = tape.nodes[a].value;
+                if a_value > F::ZERO {
+                    out.push((a, grad));
+                } else if a_value < F::ZERO {
+                    out.push((a, -grad));
                 }
-                Operation::None => {}
             }
-
-            for parent in node.parents.iter() {
-                let mut parent_grad = parent.grad.borrow_mut();
-                *parent_grad = parent_grad.max(-100.0).min(100.0);
+        }
+        Operation::Clamp => {
+            if let &[x, min, max] = parents {
+                let x_value = tape.nodes[x].value;
+                let picked = if x_value < tape.nodes[min].value {
+                    min
+                } else if x_value > tape.nodes[max].value {
+                    max
+                } else {
+                    x
+                };
+                out.push((picked, grad));
+            }
+        }
+        Operation::Select => {
+            if let &[cond, a, b] = parents {
+                let picked = if tape.nodes[cond].value > F::ZERO {
+                    a
+                } else {
+                    b
+                };
+                out.push((picked, grad));
+            }
+        }
+        Operation::Sum => {
+            out.extend(parents.iter().map(|&parent| (parent, grad)));
+        }
+        Operation::Mean => {
+            let share = grad / F::from_f64(parents.len() as f64);
+            out.extend(parents.iter().map(|&parent| (parent, share)));
+        }
+        Operation::Dot => {
+            // The first half of the parents is `a`, the second half `b`.
+            let (a, b) = parents.split_at(parents.len() / 2);
+            for (&x, &y) in a.iter().zip(b) {
+                out.push((x, grad * tape.nodes[y].value));
+                out.push((y, grad * tape.nodes[x].value));
+            }
+        }
+        Operation::Custom(slot) => {
+            let inputs: Vec<F> = parents.iter().map(|&p| tape.nodes[p].value).collect();
+            let partials = tape.custom_op(slot).backward(&inputs, value);
+            assert_eq!(
+                partials.len(),
+                parents.len(),
+                "CustomOp::backward returned the wrong number of partials"
+            );
+            for (&parent, partial) in parents.iter().zip(partials) {
+                out.push((parent, grad * partial));
             }
         }
+        Operation::Checkpoint
+        | Operation::CheckpointOutput
+        | Operation::Detach
+        | Operation::Constant
+        | Operation::None => {}
     }
+}
 
-    pub fn zero_grad(&self) {
-        *self.0.grad.borrow_mut() = 0.0;
+impl From<f64> for Value<f64> {
+    fn from(value: f64) -> Self {
+        Value::leaf(value)
     }
 }
 
-impl From<f64> for Value {
-    fn from(value: f64) -> Self {
-        Value::new(value)
+impl From<f32> for Value<f32> {
+    fn from(value: f32) -> Self {
+        Value::leaf(value)
     }
 }
 
-impl From<u8> for Value {
+impl<F: Float> From<u8> for Value<F> {
     fn from(value: u8) -> Self {
-        Value::new(value as f64)
+        Value::leaf(F::from_f64(value as f64))
     }
 }
 
-impl From<usize> for Value {
+impl<F: Float> From<usize> for Value<F> {
     fn from(value: usize) -> Self {
-        Value::new(value as f64)
+        Value::leaf(F::from_f64(value as f64))
     }
 }
@@ -0,0 +1,71 @@
+use grad::tape::DetectAnomaly;
+use grad::{Activation, Loss, Model, Value};
+
+#[test]
+#[should_panic(expected = "Anomaly detected: Log produced NaN")]
+fn log_of_a_negative_number() {
+    let _detect = DetectAnomaly::new();
+    let x = Value::new(-2.0);
+    let _ = (&x * 3.0).ln();
+}
+
+#[test]
+#[should_panic(expected = "Anomaly detected: Div produced inf")]
+fn division_by_zero() {
+    let _detect = DetectAnomaly::new();
+    let x = Value::new(1.0);
+    let _ = &x / &(&x - 1.0);
+}
+
+#[test]
+#[should_panic(expected = "path: #0 leaf -> #1 Mul -> #2 Exp -> #3 Exp")]
+fn forward_reports_include_the_path() {
+    let _detect = DetectAnomaly::new();
+    let x = Value::new(10.0);
+    let _ = (&x * &x).exp().exp();
+}
+
+#[test]
+#[should_panic(expected = "Anomaly detected: backward of Div passed -inf")]
+fn gradient_overflowing_in_backward() {
+    let _detect = DetectAnomaly::new();
+    let x = Value::new(1e-200);
+    // The value is fine, only the derivative -1 / x^2 overflows.
+    let y = 1.0 / &x;
+    assert_eq!(y.value(), 1e200);
+    y.backward();
+}
+
+#[test]
+fn anomalies_pass_silently_when_not_detected() {
+    let x = Value::new(-2.0);
+    assert!(x.ln().value().is_nan());
+
+    let y = Value::new(1e-200);
+    (1.0 / &y).backward();
+    assert!(y.grad().is_infinite());
+}
+
+#[test]
+fn detection_ends_with_the_guard() {
+    {
+        let _detect = DetectAnomaly::new();
+        let _ = Value::new(4.0).sqrt();
+    }
+    assert!(Value::new(-1.0).sqrt().value().is_nan());
+}
+
+#[test]
+fn softmax_models_run_under_detection() {
+    let _detect = DetectAnomaly::new();
+    let mut model = Model::new(&[3, 4, 2], &[Activation::ReLU, Activation::Softmax]);
+    let data = vec![
+        (vec![0.5, -1.0, 2.0], vec![1.0, 0.0]),
+        (vec![-0.3, 0.8, 0.1], vec![0.0, 1.0]),
+    ];
+
+    let prediction = model.predict(&data[0].0);
+    assert!((prediction.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    model.train(&data, 2, 0.1, Loss::CrossEntropy);
+    model.train_parallel(&data, 2, 0.1, Loss::CrossEntropy, 2);
+}
@@ -0,0 +1,18 @@
+use grad::Value;
+
+#[test]
+fn backward_through_deep_sum() {
+    let depth = 10_000_000;
+    let start = Value::new(0.0);
+    let one = Value::new(1.0);
+
+    let mut sum = start;
+    for _ in 0..depth {
+        sum = &sum + &one;
+    }
+    sum.backward();
+
+    assert_eq!(sum.value(), depth as f64);
+    assert_eq!(start.grad(), 1.0);
+    assert_eq!(one.grad(), depth as f64);
+}
@@ -0,0 +1,110 @@
+use grad::tape::{self, Scope};
+use grad::{Activation, Checkpoints, Loss, Model, Value};
+
+/// A few steps of a recurrence mixing `state` with the parameters.
+fn steps(state: &Value, params: &[Value]) -> Value {
+    params.iter().fold(*state, |state, param| {
+        (&(&state * param) + &(&state * &state)).tanh()
+    })
+}
+
+/// Gradients of the parameters after `run` builds a loss from them and
+/// backpropagates it.
+fn gradients(params: &[f64], run: impl Fn(&[Value]) -> Vec<f64>) -> Vec<f64> {
+    let _scope = Scope::new();
+    let params: Vec<Value> = params.iter().map(|&x| Value::new(x)).collect();
+    run(&params)
+}
+
+#[test]
+fn backward_matches_plain_backward() {
+    let params = [0.4, -0.7, 1.3, 0.2];
+    let plain = gradients(&params, |params| {
+        let x = Value::new(0.5);
+        let hidden = steps(&steps(&x, params), params);
+        (&steps(&hidden, &params[1..]) * &params[0]).backward();
+        [&[x], params].concat().iter().map(Value::grad).collect()
+    });
+
+    let checkpointed = gradients(&params, |params| {
+        let x = Value::new(0.5);
+        let checkpoints = Checkpoints::new();
+        // The outer segment reads the parameters and nests another one.
+        let hidden = checkpoints.segment(|checkpoints| {
+            let inner = checkpoints.segment(|_| vec![steps(&x, params)])[0];
+            vec![steps(&inner, params)]
+        })[0];
+        let tail = checkpoints.segment(move |_| vec![steps(&hidden, &params[1..])])[0];
+        checkpoints.backward(&(&tail * &params[0]));
+        [&[x], params].concat().iter().map(Value::grad).collect()
+    });
+
+    assert_eq!(checkpointed, plain);
+}
+
+fn xor() -> Vec<(Vec<f64>, Vec<f64>)> {
+    [
+        (0.0, 0.0, 0.0),
+        (0.0, 1.0, 1.0),
+        (1.0, 0.0, 1.0),
+        (1.0, 1.0, 0.0),
+    ]
+    .into_iter()
+    .map(|(a, b, y)| (vec![a, b], vec![y]))
+    .collect()
+}
+
+#[test]
+fn checkpointed_training_matches_plain_training() {
+    let mut plain = Model::new(&[2, 4, 1], &[Activation::Sigmoid, Activation::Sigmoid]);
+    let mut checkpointed = plain.cast::<f64>();
+    checkpointed.set_checkpointing(Some(3));
+
+    plain.train(&xor(), 5, 0.5, Loss::MSE);
+    checkpointed.train(&xor(), 5, 0.5, Loss::MSE);
+
+    for (input, _) in xor() {
+        let (expected, actual) = (plain.predict(&input)[0], checkpointed.predict(&input)[0]);
+        assert!(
+            (expected - actual).abs() < 1e-12,
+            "{} vs {}",
+            expected,
+            actual
+        );
+    }
+}
+
+/// Most nodes on the tape at once while `run` builds and differentiates a
+/// deep chain.
+fn peak_nodes(run: impl Fn(&Value) -> Value) -> usize {
+    let _scope = Scope::new();
+    let start = tape::len::<f64>();
+    tape::reset_peak::<f64>();
+    let x = Value::new(0.1);
+    let y = run(&x);
+    assert_ne!(x.grad(), 0.0, "{} was not differentiated", y.value());
+    tape::stats::<f64>().peak_nodes - start
+}
+
+#[test]
+fn segments_bound_the_size_of_the_tape() {
+    let chain = |x: &Value, length: usize| (0..length).fold(*x, |y, _| (&y * 0.9).sin());
+
+    let plain = peak_nodes(|x| {
+        let y = chain(x, 2000);
+        y.backward();
+        y
+    });
+
+    let checkpointed = peak_nodes(|x| {
+        let checkpoints = Checkpoints::new();
+        let y = (0..20).fold(*x, |y, _| {
+            checkpoints.segment(move |_| vec![chain(&y, 100)])[0]
+        });
+        checkpoints.backward(&y);
+        y
+    });
+
+    assert!(plain > 6000, "{}", plain);
+    assert!(checkpointed < plain / 10, "{} vs {}", checkpointed, plain);
+}
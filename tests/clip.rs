@@ -0,0 +1,58 @@
+use grad::{Activation, GradientClip, Model};
+
+/// Two layers with norms 5 and 13.
+fn grads() -> Vec<f64> {
+    vec![3.0, -4.0, 5.0, 12.0, 0.0]
+}
+
+fn clipped(clip: GradientClip) -> Vec<f64> {
+    let mut grads = grads();
+    clip.apply(&mut grads, &[2, 3]);
+    grads
+}
+
+#[test]
+fn none_keeps_the_gradients() {
+    assert_eq!(clipped(GradientClip::None), grads());
+}
+
+#[test]
+fn value_clamps_every_gradient() {
+    assert_eq!(
+        clipped(GradientClip::Value(4.5)),
+        vec![3.0, -4.0, 4.5, 4.5, 0.0]
+    );
+}
+
+#[test]
+fn global_norm_rescales_all_gradients_together() {
+    // The combined norm is sqrt(25 + 169) = sqrt(194).
+    let scale = 7.0 / 194f64.sqrt();
+    let expected: Vec<f64> = grads().iter().map(|grad| grad * scale).collect();
+    assert_eq!(clipped(GradientClip::GlobalNorm(7.0)), expected);
+    assert_eq!(clipped(GradientClip::GlobalNorm(20.0)), grads());
+}
+
+#[test]
+fn layer_norm_rescales_every_layer_on_its_own() {
+    // Only the second layer is above the bound.
+    let scale = 6.5 / 13.0;
+    assert_eq!(
+        clipped(GradientClip::LayerNorm(6.5)),
+        vec![3.0, -4.0, 5.0 * scale, 12.0 * scale, 0.0]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Gradient clip bound must be a non-negative number, got -1")]
+fn negative_bounds_are_rejected() {
+    let mut model: Model = Model::new(&[2, 1], &[Activation::Linear]);
+    model.set_gradient_clip(GradientClip::Value(-1.0));
+}
+
+#[test]
+#[should_panic(expected = "Gradient clip bound must be a non-negative number, got NaN")]
+fn nan_bounds_are_rejected() {
+    let mut model: Model = Model::new(&[2, 1], &[Activation::Linear]);
+    model.set_gradient_clip(GradientClip::GlobalNorm(f64::NAN));
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use grad::autograd::grad;
+use grad::{CustomOp, Value};
+
+/// Claims to take two inputs but only ever returns one partial.
+struct ForgetfulProduct;
+
+impl CustomOp for ForgetfulProduct {
+    fn name(&self) -> &str {
+        "ForgetfulProduct"
+    }
+
+    fn forward(&self, inputs: &[f64]) -> f64 {
+        inputs[0] * inputs[1]
+    }
+
+    fn backward(&self, inputs: &[f64], _output: f64) -> Vec<f64> {
+        vec![inputs[1]]
+    }
+}
+
+#[test]
+#[should_panic(expected = "CustomOp::backward returned the wrong number of partials")]
+fn backward_checks_the_number_of_partials() {
+    let (a, b) = (Value::new(2.0), Value::new(3.0));
+    Value::custom(&Arc::new(ForgetfulProduct), &[&a, &b]).backward();
+}
+
+#[test]
+#[should_panic(expected = "CustomOp::backward returned the wrong number of partials")]
+fn grad_checks_the_number_of_partials() {
+    let (a, b) = (Value::new(2.0), Value::new(3.0));
+    let y = Value::custom(&Arc::new(ForgetfulProduct), &[&a, &b]);
+    grad(&[y], &[a, b], true);
+}
@@ -0,0 +1,46 @@
+use grad::Value;
+use grad::tape::Scope;
+
+#[test]
+fn prints_deep_chains_without_recursing() {
+    let _scope = Scope::new();
+    let x = Value::new(1.0);
+    x.set_name("x");
+    let mut y = x;
+    for _ in 0..100_000 {
+        y = y.sin();
+    }
+
+    let printed = y.to_string();
+    assert!(printed.starts_with("sin(sin("));
+    assert!(printed.contains('…'));
+    assert!(printed.len() < 2_000);
+}
+
+#[test]
+fn shortens_repeated_subexpressions() {
+    // Every step uses the previous one twice, printing it in full would
+    // take 2^64 copies of x.
+    let _scope = Scope::new();
+    let x = Value::new(1.0);
+    x.set_name("x");
+    let mut y = x;
+    for _ in 0..64 {
+        y = &y * &y;
+    }
+
+    let printed = y.to_string();
+    assert_eq!(printed, "… * …");
+}
+
+#[test]
+fn prints_short_expressions_in_full() {
+    let x = Value::new(0.5);
+    x.set_name("x");
+    let mut y = x;
+    for _ in 0..3 {
+        y = &y * &y;
+    }
+
+    assert_eq!(y.to_string(), "x * x * x * x * x * x * x * x");
+}
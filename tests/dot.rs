@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use grad::{CustomOp, DotOptions, Value};
+
+struct Braces;
+
+impl CustomOp for Braces {
+    fn name(&self) -> &str {
+        "{a|b} <\"c\">"
+    }
+
+    fn forward(&self, inputs: &[f64]) -> f64 {
+        inputs[0]
+    }
+
+    fn backward(&self, _inputs: &[f64], _output: f64) -> Vec<f64> {
+        vec![1.0]
+    }
+}
+
+#[test]
+fn escapes_record_syntax_in_labels() {
+    let x = Value::new(1.0);
+    let y = Value::custom(&Arc::new(Braces), &[&x]);
+    let dot = y.to_dot(&DotOptions::default());
+
+    assert!(
+        dot.contains(r#"#1 \{a\|b\} \<\"c\"\> | value 1.0000"#),
+        "{}",
+        dot
+    );
+}
+
+#[test]
+fn labels_leaves_and_constants() {
+    let x = Value::new(2.0);
+    let y = &x * 3.0;
+    let dot = y.to_dot(&DotOptions::default());
+
+    assert!(dot.contains("{ #0 leaf | value 2.0000"), "{}", dot);
+    assert!(dot.contains("{ #1 constant | value 3.0000"), "{}", dot);
+    assert!(dot.contains("{ #2 Mul | value 6.0000"), "{}", dot);
+}
+
+#[test]
+fn collapsed_constants_move_into_the_label() {
+    let x = Value::new(2.0);
+    let y = &x * 3.0;
+    let options = DotOptions {
+        collapse_constants: true,
+        ..DotOptions::default()
+    };
+    let dot = y.to_dot(&options);
+
+    assert!(
+        dot.contains("{ #2 Mul | value 6.0000 | grad 0.0000 | const 3.0000 }"),
+        "{}",
+        dot
+    );
+    assert!(dot.contains("n0 -> n2;"), "{}", dot);
+    assert!(!dot.contains("n1"), "{}", dot);
+}
+
+#[test]
+fn nodes_past_max_depth_are_cut_off() {
+    let x = Value::new(0.5);
+    let y = x.exp().sin().tanh();
+    let options = DotOptions {
+        max_depth: Some(1),
+        ..DotOptions::default()
+    };
+    let dot = y.to_dot(&options);
+
+    // `sin` is kept, but its input is not.
+    assert!(
+        dot.contains("n2 [label=\"{ #2 Sin | value 0.9970 | grad 0.0000 }\", style=dashed];"),
+        "{}",
+        dot
+    );
+    assert!(
+        dot.contains("n3 [label=\"{ #3 Tanh | value 0.7603 | grad 0.0000 }\"];"),
+        "{}",
+        dot
+    );
+    assert!(dot.contains("n2 -> n3;"), "{}", dot);
+    assert!(!dot.contains("n1"), "{}", dot);
+    assert!(!dot.contains("n0"), "{}", dot);
+}
@@ -0,0 +1,56 @@
+use grad::gradcheck::gradcheck;
+use grad::{Activation, Loss, Model, Value};
+
+#[test]
+fn vanishing_gradients_are_compared_absolutely() {
+    // The gradient at 0 is exactly zero, the finite difference is only
+    // rounding noise. Dividing by that noise would report a huge error.
+    let report = gradcheck(|x| x[0].cos(), &[0.0], 1e-6, 1e-6);
+
+    assert!(report.passed(), "{}", report);
+    assert!(report.entries[0].relative_error < 1e-9);
+}
+
+#[test]
+fn large_gradients_are_compared_relatively() {
+    let report = gradcheck(|x| &x[0] * 1e6, &[1.0], 1e-3, 1e-6);
+
+    assert!(report.passed(), "{}", report);
+    assert!(report.entries[0].analytic == 1e6);
+}
+
+#[test]
+fn wrong_gradients_fail() {
+    // detach hides the dependence on x from backward but not from the
+    // finite differences.
+    let report = gradcheck(|x| &x[0] * &x[0].detach(), &[3.0], 1e-6, 1e-6);
+
+    assert!(!report.passed());
+    assert_eq!(report.failures(), vec![0]);
+}
+
+#[test]
+fn checks_single_precision() {
+    let report = gradcheck(
+        |x: &[Value<f32>]| (&x[0] * &x[1]).tanh(),
+        &[0.5f32, -0.25],
+        1e-2,
+        1e-3,
+    );
+
+    assert!(report.passed(), "{}", report);
+}
+
+#[test]
+fn model_gradients_match_finite_differences() {
+    let model: Model = Model::new(&[2, 3, 2], &[Activation::Sigmoid, Activation::Softmax]);
+    let data = vec![
+        (vec![0.5, -1.0], vec![1.0, 0.0]),
+        (vec![-0.3, 0.8], vec![0.0, 1.0]),
+    ];
+    let report = model.gradcheck(&data, Loss::CrossEntropy, 1e-6, 1e-6);
+
+    // One entry per weight and bias.
+    assert_eq!(report.entries.len(), 3 * 3 + 2 * 4);
+    assert!(report.passed(), "{}", report);
+}
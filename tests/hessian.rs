@@ -0,0 +1,29 @@
+use grad::Value;
+use grad::autograd::{hessian, hvp};
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-12,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn hvp_matches_the_hessian() {
+    let f = |v: &[Value]| &(&(&v[0] * &v[0]) * &v[1]) + &v[1].sin();
+    let point = [1.2, -0.7];
+    let vector = [0.3, 2.0];
+
+    let h = hessian(f, &point);
+    let product = hvp(f, &point, &vector);
+
+    assert_close(h[(0, 0)], 2.0 * -0.7);
+    assert_close(h[(0, 1)], 2.0 * 1.2);
+    assert_close(h[(1, 0)], h[(0, 1)]);
+    assert_close(h[(1, 1)], -(-0.7f64).sin());
+    for (row, &value) in product.iter().enumerate() {
+        assert_close(value, h.row(row)[0] * vector[0] + h.row(row)[1] * vector[1]);
+    }
+}
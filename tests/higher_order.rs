@@ -0,0 +1,76 @@
+use grad::Value;
+use grad::autograd::grad;
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-12,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn second_derivative_of_a_cubic() {
+    let x = Value::new(1.5);
+    let y = &(&x * &x) * &x;
+    let dy = grad(&[y], &[x], true)[0];
+    let d2y = grad(&[dy], &[x], true)[0];
+    let d3y = grad(&[d2y], &[x], false)[0];
+
+    assert_close(dy.value(), 3.0 * 1.5 * 1.5);
+    assert_close(d2y.value(), 6.0 * 1.5);
+    assert_close(d3y.value(), 6.0);
+}
+
+fn second_derivative(x: f64, f: fn(&Value) -> Value) -> f64 {
+    let x = Value::new(x);
+    let dy = grad(&[f(&x)], &[x], true)[0];
+    grad(&[dy], &[x], true)[0].value()
+}
+
+#[test]
+fn second_derivatives_of_transcendental_functions() {
+    let t = 0.4f64.tanh();
+    assert_close(second_derivative(0.4, Value::sin), -0.4f64.sin());
+    assert_close(second_derivative(0.4, Value::exp), 0.4f64.exp());
+    assert_close(
+        second_derivative(0.4, Value::tanh),
+        -2.0 * t * (1.0 - t * t),
+    );
+    assert_close(second_derivative(0.4, Value::ln), -1.0 / (0.4 * 0.4));
+}
+
+#[test]
+fn mixed_partials_agree() {
+    // f = x^2 y^3, d2f/dxdy = 6 x y^2 either way round.
+    let x = Value::new(2.0);
+    let y = Value::new(-1.0);
+    let f = &(&x * &x) * &(&(&y * &y) * &y);
+    let partials = grad(&[f], &[x, y], true);
+    let dxdy = grad(&[partials[0]], &[y], true)[0];
+    let dydx = grad(&[partials[1]], &[x], true)[0];
+
+    assert_close(dxdy.value(), 6.0 * 2.0 * 1.0);
+    assert_close(dydx.value(), dxdy.value());
+}
+
+#[test]
+fn grad_leaves_the_grad_fields_alone() {
+    let x = Value::new(3.0);
+    grad(&[&x * &x], &[x], true);
+    assert_eq!(x.grad(), 0.0);
+}
+
+#[test]
+fn gradient_penalty_backpropagates() {
+    // L = (dy/dx)^2 with y = w x^2, so dy/dx = 2 w x and dL/dw = 8 w x^2.
+    let w = Value::new(0.5);
+    let x = Value::new(3.0);
+    let y = &w * &(&x * &x);
+    let slope = grad(&[y], &[x], true)[0];
+    let penalty = &slope * &slope;
+    penalty.backward();
+
+    assert_close(w.grad(), 8.0 * 0.5 * 9.0);
+}
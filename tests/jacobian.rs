@@ -0,0 +1,74 @@
+use grad::Value;
+use grad::autograd::{backward, jacobian};
+
+fn softmax(logits: &[Value]) -> Vec<Value> {
+    let exps: Vec<Value> = logits.iter().map(Value::exp).collect();
+    let total = Value::sum(&exps);
+    exps.iter().map(|e| e / &total).collect()
+}
+
+#[test]
+fn seeded_backward_scales_the_gradients() {
+    let f = |x: &Value| &(x * x) + &x.sin();
+    let x = Value::new(2.0);
+    f(&x).backward();
+    let unit = x.grad();
+    assert!((unit - (4.0 + 2.0f64.cos())).abs() < 1e-12);
+
+    // Scaling by a power of two is exact, so the gradients are too.
+    x.zero_grad();
+    f(&x).backward_with(-0.5);
+    assert_eq!(x.grad(), -0.5 * unit);
+}
+
+#[test]
+fn multi_root_backward_is_a_vector_jacobian_product() {
+    let x = Value::new(1.5);
+    let y = Value::new(-2.0);
+    let outputs = [&x * &y, &x + &y, x.exp()];
+    backward(&outputs, &[1.0, 10.0, 100.0]);
+
+    assert_eq!(x.grad(), -2.0 + 10.0 + 100.0 * 1.5f64.exp());
+    assert_eq!(y.grad(), 1.5 + 10.0);
+}
+
+#[test]
+fn roots_feeding_into_each_other_count_once_per_seed() {
+    let x = Value::new(3.0);
+    let square = &x * &x;
+    let shifted = &square + 1.0;
+    backward(&[square, shifted], &[1.0, 2.0]);
+    assert_eq!(x.grad(), 3.0 * 6.0);
+}
+
+#[test]
+fn jacobian_of_a_linear_map_is_its_matrix() {
+    let j = jacobian(
+        |v| vec![&(&v[0] * 2.0) - &v[1], &v[1] * 3.0, Value::sum(v)],
+        &[0.4, 0.9],
+    );
+    assert_eq!((j.rows(), j.cols()), (3, 2));
+    assert_eq!(j.data(), &[2.0, -1.0, 0.0, 3.0, 1.0, 1.0]);
+}
+
+#[test]
+fn jacobian_of_softmax() {
+    let logits = [0.5, -1.0, 2.0];
+    let j = jacobian(softmax, &logits);
+
+    let exps: Vec<f64> = logits.iter().map(|x: &f64| x.exp()).collect();
+    let total: f64 = exps.iter().sum();
+    let p: Vec<f64> = exps.iter().map(|e| e / total).collect();
+    for i in 0..3 {
+        for k in 0..3 {
+            let expected = if i == k {
+                p[i] * (1.0 - p[i])
+            } else {
+                -p[i] * p[k]
+            };
+            assert!((j[(i, k)] - expected).abs() < 1e-12, "J[{}][{}]", i, k);
+        }
+        // The outputs always sum to one.
+        assert!(j.row(i).iter().sum::<f64>().abs() < 1e-12);
+    }
+}
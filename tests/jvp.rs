@@ -0,0 +1,111 @@
+use grad::autograd::jacobian;
+use grad::dual::{Dual, jvp};
+use grad::{Activation, Value};
+
+/// Checks that forward mode gives the outputs of `backward_f` and its
+/// Jacobian times `v`.
+fn assert_jvp_matches(
+    forward_f: impl Fn(&[Dual]) -> Vec<Dual>,
+    backward_f: impl Fn(&[Value]) -> Vec<Value>,
+    x: &[f64],
+    v: &[f64],
+) {
+    let (outputs, product) = jvp(forward_f, x, v);
+
+    let leaves: Vec<Value> = x.iter().map(|&x| Value::new(x)).collect();
+    let expected: Vec<f64> = backward_f(&leaves).iter().map(Value::value).collect();
+    assert_eq!(outputs, expected);
+
+    let expected = jacobian(backward_f, x).mul_vec(v);
+    for (actual, expected) in product.iter().zip(&expected) {
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "{:?} vs {:?} at {:?}",
+            product,
+            expected,
+            x
+        );
+    }
+}
+
+#[test]
+fn jvp_is_the_jacobian_times_the_direction() {
+    assert_jvp_matches(
+        |x| {
+            vec![
+                x[0] * x[1] + x[2].sin(),
+                x[0].exp() / x[2],
+                (x[1] ^ 3.0).tanh(),
+            ]
+        },
+        |x| {
+            vec![
+                &(&x[0] * &x[1]) + &x[2].sin(),
+                &x[0].exp() / &x[2],
+                (&x[1] ^ 3.0).tanh(),
+            ]
+        },
+        &[0.3, -0.8, 1.7],
+        &[1.0, 2.0, -0.5],
+    );
+
+    assert_jvp_matches(
+        |x| vec![x[0].atan2(x[1]), (x[0] * x[0] + x[1] * x[1]).sqrt().ln()],
+        |x| {
+            vec![
+                x[0].atan2(&x[1]),
+                (&(&x[0] * &x[0]) + &(&x[1] * &x[1])).sqrt().ln(),
+            ]
+        },
+        &[-1.5, 0.4],
+        &[0.25, -3.0],
+    );
+}
+
+#[test]
+fn activations_agree_between_modes() {
+    let x = [0.5, -1.2, 2.0, 0.0];
+    let v = [1.0, -0.5, 0.25, 2.0];
+    for activation in [
+        Activation::Linear,
+        Activation::Sigmoid,
+        Activation::ReLU,
+        Activation::Softmax,
+    ] {
+        assert_jvp_matches(
+            |x| activation.apply_dual(x),
+            |x| activation.apply(x),
+            &x,
+            &v,
+        );
+    }
+}
+
+/// Derivative of `forward` at `x` and the gradient `backward` gives it.
+fn unary(x: f64, forward: fn(Dual) -> Dual, backward: fn(&Value) -> Value) -> (f64, f64) {
+    let value = Value::new(x);
+    backward(&value).backward();
+    (forward(Dual::variable(x)).derivative, value.grad())
+}
+
+/// Directional derivative of `forward` at the origin along (1, 1), and the
+/// sum of the gradients `backward` gives there.
+fn binary(forward: fn(Dual, Dual) -> Dual, backward: fn(&Value, &Value) -> Value) -> (f64, f64) {
+    let (a, b) = (Value::new(0.0), Value::new(0.0));
+    backward(&a, &b).backward();
+    let derivative = forward(Dual::variable(0.0), Dual::variable(0.0)).derivative;
+    (derivative, a.grad() + b.grad())
+}
+
+#[test]
+fn singular_points_have_the_derivatives_of_backward() {
+    for x in [0.0, -1.0] {
+        let (forward, backward) = unary(x, Dual::ln, Value::ln);
+        assert_eq!(forward, backward, "ln at {}", x);
+        let (forward, backward) = unary(x, Dual::sqrt, Value::sqrt);
+        assert_eq!(forward, backward, "sqrt at {}", x);
+    }
+
+    assert_eq!(binary(|a, b| a / b, |a, b| a / b), (0.0, 0.0));
+    assert_eq!(binary(Dual::atan2, |a, b| a.atan2(b)), (0.0, 0.0));
+}
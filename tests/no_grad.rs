@@ -0,0 +1,44 @@
+use grad::tape::{self, NoGrad};
+use grad::{Activation, Model, Value};
+
+#[test]
+fn operations_record_no_parents() {
+    let x = Value::new(3.0);
+    let y = {
+        let _no_grad = NoGrad::new();
+        (&x * &x).exp()
+    };
+    assert!(!y.requires_grad());
+    assert_eq!(y.value(), 9f64.exp());
+
+    // The result is a leaf, so nothing reaches `x`.
+    y.backward();
+    assert_eq!(x.grad(), 0.0);
+
+    let z = &x * &x;
+    assert!(z.requires_grad());
+}
+
+#[test]
+fn nested_guards_restore_the_previous_state() {
+    assert!(tape::is_grad_enabled());
+    {
+        let _outer = NoGrad::new();
+        {
+            let _inner = NoGrad::new();
+            assert!(!tape::is_grad_enabled());
+        }
+        assert!(!tape::is_grad_enabled());
+        assert!(!(&Value::new(1.0) + 1.0).requires_grad());
+    }
+    assert!(tape::is_grad_enabled());
+}
+
+#[test]
+fn predict_leaves_the_tape_alone() {
+    let model: Model = Model::new(&[3, 4, 2], &[Activation::ReLU, Activation::Softmax]);
+    let before = tape::len::<f64>();
+    let output = model.predict(&[0.5, -1.0, 2.0]);
+    assert_eq!(output.len(), 2);
+    assert_eq!(tape::len::<f64>(), before);
+}
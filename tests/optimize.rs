@@ -0,0 +1,163 @@
+use grad::Value;
+use grad::tape::{self, Optimize, Scope};
+
+/// Value and input gradients of `f` at `inputs`, built with or without the
+/// optimizer, and the number of nodes it recorded.
+fn evaluate(
+    optimize: bool,
+    inputs: &[f64],
+    f: impl Fn(&[Value]) -> Value,
+) -> (f64, Vec<f64>, usize) {
+    let _scope = Scope::new();
+    let _optimize = optimize.then(Optimize::new);
+    let leaves: Vec<Value> = inputs.iter().map(|&x| Value::new(x)).collect();
+    let start = tape::len::<f64>();
+    let output = f(&leaves);
+    let nodes = tape::len::<f64>() - start;
+    output.backward();
+    (
+        output.value(),
+        leaves.iter().map(Value::grad).collect(),
+        nodes,
+    )
+}
+
+/// Distance between `a` and `b` in units in the last place.
+fn ulps(a: f64, b: f64) -> u64 {
+    let key = |x: f64| {
+        let bits = x.to_bits() as i64;
+        if bits < 0 { i64::MIN - bits } else { bits }
+    };
+    key(a).abs_diff(key(b))
+}
+
+/// Runs `f` both ways, checks that the value is identical and the
+/// gradients agree to rounding, and returns how many nodes the optimizer
+/// saved.
+fn compare(inputs: &[f64], f: impl Fn(&[Value]) -> Value) -> usize {
+    let (plain_value, plain_grads, plain_nodes) = evaluate(false, inputs, &f);
+    let (value, grads, nodes) = evaluate(true, inputs, &f);
+
+    assert_eq!(value.to_bits(), plain_value.to_bits());
+    for (grad, plain) in grads.iter().zip(&plain_grads) {
+        assert!(
+            ulps(*grad, *plain) <= 1,
+            "gradient {} without the optimizer, {} with it",
+            plain,
+            grad
+        );
+    }
+    plain_nodes - nodes
+}
+
+#[test]
+fn folds_operations_on_constants() {
+    compare(&[1.5], |x| {
+        let scale = &(&Value::constant(2.0) * 3.0) + 0.5;
+        &x[0] * &scale.exp()
+    });
+
+    let scale = || (&(&Value::constant(2.0) * 3.0) + 0.5).exp();
+    assert_eq!(scale().to_string(), "e^(2 * 3 + 0.5)");
+    let _optimize = Optimize::new();
+    let folded = scale();
+    assert_eq!(folded.to_string(), 6.5f64.exp().to_string());
+    assert!(!folded.requires_grad());
+}
+
+#[test]
+fn identities_return_their_operand() {
+    let identities: [fn(&Value) -> Value; 8] = [
+        |x| x + 0.0,
+        |x| 0.0 + x,
+        |x| x * 1.0,
+        |x| 1.0 * x,
+        |x| x / 1.0,
+        |x| x ^ 1.0,
+        |x| Value::sum(&[*x]),
+        |x| Value::mean(&[*x]),
+    ];
+    for identity in identities {
+        // The constant operand is the only node recorded.
+        let saved = compare(&[-0.75], |x| (&identity(&x[0]) * &x[0]).tanh());
+        assert_eq!(saved, 1);
+    }
+}
+
+#[test]
+fn identities_keep_other_operations() {
+    let saved = compare(&[2.0], |x| &(&(&x[0] + 1.0) * 2.0) ^ 3.0);
+    assert_eq!(saved, 0);
+}
+
+#[test]
+fn reuses_commuted_sums_and_products() {
+    let saved = compare(&[0.3, -1.7], |x| {
+        let product = &(&x[0] * &x[1]) + &(&x[1] * &x[0]);
+        let sum = &(&x[0] + &x[1]) * &(&x[1] + &x[0]);
+        &product + &sum
+    });
+    assert_eq!(saved, 2);
+}
+
+#[test]
+fn does_not_reuse_across_operand_order_of_other_operations() {
+    let saved = compare(&[0.3, -1.7], |x| &(&x[0] / &x[1]) + &(&x[1] / &x[0]));
+    assert_eq!(saved, 0);
+}
+
+#[test]
+fn gradients_agree_on_a_larger_graph() {
+    let inputs: Vec<f64> = (0..12).map(|i| (i as f64 * 0.37).sin()).collect();
+    compare(&inputs, |x| {
+        let mut total = Value::constant(0.0);
+        for w in x.chunks(3) {
+            let activation = (&(&(&w[0] * &x[0]) + &(&w[1] * &x[1])) + &(&w[2] * 1.0)).tanh();
+            total = &total + &(&activation * &activation);
+            total = &total + &(&(&x[0] * &w[0]) * 0.5);
+        }
+        &total / 4.0
+    });
+}
+
+#[test]
+fn truncated_expressions_are_not_reused() {
+    let _optimize = Optimize::new();
+    let x = Value::new(2.0);
+    let y = Value::new(3.0);
+    {
+        let _scope = Scope::new();
+        let _ = &x * &y;
+    }
+
+    let start = tape::len::<f64>();
+    let product = &x * &y;
+    assert_eq!(tape::len::<f64>(), start + 1);
+    product.backward();
+    assert_eq!(product.value(), 6.0);
+    assert_eq!(x.grad(), 3.0);
+}
+
+#[test]
+fn changed_leaves_are_not_reused() {
+    let _optimize = Optimize::new();
+    let x = Value::new(2.0);
+    let first = &x * 2.0;
+    x.update_value(5.0);
+    let second = &x * 2.0;
+    assert_eq!(first.value(), 4.0);
+    assert_eq!(second.value(), 10.0);
+}
+
+#[test]
+fn constants_are_separate_inputs() {
+    let _optimize = Optimize::new();
+    let start = tape::len::<f64>();
+    let a = Value::constant(1.0);
+    let b = Value::constant(1.0);
+    assert_eq!(tape::len::<f64>(), start + 2);
+
+    a.update_value(4.0);
+    assert_eq!(a.value(), 4.0);
+    assert_eq!(b.value(), 1.0);
+}
@@ -0,0 +1,53 @@
+use grad::{Activation, Loss, Model};
+
+// Models are shared between training threads.
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+    let _ = assert_send_sync::<Model>;
+    let _ = assert_send_sync::<Model<f32>>;
+};
+
+fn data() -> Vec<(Vec<f64>, Vec<f64>)> {
+    (0..7)
+        .map(|i| {
+            let x = i as f64 / 3.0 - 1.0;
+            (vec![x, x * x], vec![(2.0 * x).sin()])
+        })
+        .collect()
+}
+
+#[test]
+fn train_parallel_matches_train() {
+    let model: Model = Model::new(&[2, 5, 1], &[Activation::Sigmoid, Activation::Linear]);
+    let mut sequential = model.cast::<f64>();
+    sequential.train(&data(), 5, 0.1, Loss::MSE);
+
+    // Uneven chunks are weighted by their size.
+    for threads in [1, 3, 4] {
+        let mut parallel = model.cast::<f64>();
+        parallel.train_parallel(&data(), 5, 0.1, Loss::MSE, threads);
+
+        for (input, _) in data() {
+            let (expected, actual) = (sequential.predict(&input)[0], parallel.predict(&input)[0]);
+            assert!(
+                (expected - actual).abs() < 1e-12,
+                "{} threads: {} vs {}",
+                threads,
+                expected,
+                actual
+            );
+        }
+    }
+}
+
+#[test]
+fn training_on_no_data_keeps_the_parameters() {
+    let mut model: Model = Model::new(&[2, 3, 1], &[Activation::ReLU, Activation::Linear]);
+    let input = [0.5, -0.25];
+    let before = model.predict(&input);
+
+    model.train(&[], 3, 0.1, Loss::MSE);
+    assert_eq!(model.predict(&input), before);
+    model.train_parallel(&[], 3, 0.1, Loss::MSE, 2);
+    assert_eq!(model.predict(&input), before);
+}
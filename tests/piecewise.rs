@@ -0,0 +1,85 @@
+use grad::Value;
+use grad::dual::Dual;
+
+/// Gradients of `a` and `b` after backpropagating `f(a, b)`.
+fn grads(a: f64, b: f64, f: impl Fn(&Value, &Value) -> Value) -> (f64, f64) {
+    let a = Value::new(a);
+    let b = Value::new(b);
+    f(&a, &b).backward();
+    (a.grad(), b.grad())
+}
+
+#[test]
+fn max_and_min_route_the_gradient_to_the_picked_operand() {
+    let max = |a: &Value, b: &Value| a.max(b);
+    let min = |a: &Value, b: &Value| a.min(b);
+
+    assert_eq!(grads(3.0, 1.0, max), (1.0, 0.0));
+    assert_eq!(grads(-3.0, 1.0, max), (0.0, 1.0));
+    assert_eq!(grads(3.0, 1.0, min), (0.0, 1.0));
+    assert_eq!(grads(-3.0, 1.0, min), (1.0, 0.0));
+    // Ties go to the first operand only, not half to each.
+    assert_eq!(grads(2.0, 2.0, max), (1.0, 0.0));
+    assert_eq!(grads(2.0, 2.0, min), (1.0, 0.0));
+}
+
+#[test]
+fn relu_through_max_has_no_gradient_below_zero() {
+    let relu = |x: &Value, zero: &Value| x.max(zero);
+    let (x_grad, zero_grad) = grads(-0.5, 0.0, relu);
+    assert_eq!(x_grad, 0.0);
+    assert_eq!(zero_grad, 1.0);
+}
+
+#[test]
+fn abs_uses_the_sign_with_zero_at_the_kink() {
+    let cases = [(2.5, 1.0), (-2.5, -1.0), (0.0, 0.0), (-0.0, 0.0)];
+    for (x, expected) in cases {
+        let x = Value::new(x);
+        let y = x.abs();
+        y.backward();
+        assert_eq!(y.value(), x.value().abs());
+        assert_eq!(x.grad(), expected, "d|x|/dx at {}", x.value());
+    }
+}
+
+#[test]
+fn clamp_passes_the_gradient_only_inside_the_interval() {
+    let cases = [
+        (-2.0, -1.0, 0.0),
+        (0.3, 0.3, 1.0),
+        (5.0, 1.0, 0.0),
+        (1.0, 1.0, 1.0),
+    ];
+    for (x, value, grad) in cases {
+        let x = Value::new(x);
+        let y = x.clamp(-1.0, 1.0);
+        (&y * 3.0).backward();
+        assert_eq!(y.value(), value);
+        assert_eq!(x.grad(), 3.0 * grad, "at {}", x.value());
+    }
+}
+
+#[test]
+fn select_differentiates_only_the_picked_branch() {
+    for (cond, picked) in [(1.0, 0), (0.0, 1), (-4.0, 1)] {
+        let cond = Value::new(cond);
+        let a = Value::new(10.0);
+        let b = Value::new(20.0);
+        let y = Value::select(&cond, &(&a * &a), &(&b * 2.0));
+        y.backward();
+
+        let expected = [(100.0, 20.0, 0.0), (40.0, 0.0, 2.0)][picked];
+        assert_eq!((y.value(), a.grad(), b.grad()), expected);
+        assert_eq!(cond.grad(), 0.0);
+    }
+}
+
+#[test]
+fn forward_mode_abs_agrees_with_backward() {
+    for x in [2.5, -2.5, 0.0, -0.0] {
+        let value = Value::new(x);
+        value.abs().backward();
+        assert_eq!(Dual::variable(x).abs().derivative, value.grad(), "at {}", x);
+    }
+}
@@ -0,0 +1,99 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use grad::{Activation, CustomOp, Plan, Value};
+
+#[test]
+fn hooks_registered_while_tracing_run_on_every_backward() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    let mut plan = Plan::trace(
+        move |x| {
+            let square = &x[0] * &x[0];
+            let counter = counter.clone();
+            square.register_hook(move |grad| {
+                counter.set(counter.get() + 1);
+                -grad
+            });
+            &square + &x[1]
+        },
+        &[3.0, 1.0],
+    );
+
+    assert_eq!(plan.backward(), vec![-6.0, 1.0]);
+    plan.forward(&[-2.0, 5.0]);
+    assert_eq!(plan.backward(), vec![4.0, 1.0]);
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn hooked_plans_match_backward() {
+    let f = |x: &[Value]| {
+        let hidden = (&x[0] * &x[1]).tanh();
+        hidden.register_hook(|grad| grad * 0.5);
+        x[1].register_hook(|grad| grad.clamp(-0.1, 0.1));
+        &hidden * &x[0]
+    };
+
+    let inputs = [0.7, -1.2];
+    let leaves: Vec<Value> = inputs.iter().map(|&x| Value::new(x)).collect();
+    f(&leaves).backward();
+    let expected: Vec<f64> = leaves.iter().map(Value::grad).collect();
+
+    let mut plan = Plan::trace(f, &inputs);
+    assert_eq!(plan.backward(), expected);
+}
+
+/// Rectified difference with a hand-written derivative.
+struct Hinge;
+
+impl CustomOp for Hinge {
+    fn name(&self) -> &str {
+        "Hinge"
+    }
+
+    fn forward(&self, inputs: &[f64]) -> f64 {
+        (inputs[0] - inputs[1]).max(0.0)
+    }
+
+    fn backward(&self, inputs: &[f64], _output: f64) -> Vec<f64> {
+        let active = if inputs[0] > inputs[1] { 1.0 } else { 0.0 };
+        vec![active, -active]
+    }
+}
+
+fn branching(x: &[Value]) -> Value {
+    let hinge = Value::custom(&Arc::new(Hinge), &[&x[0], &x[1]]);
+    let picked = Value::select(&(&x[2] - 0.5), &(&x[0] * &x[1]), &x[2].sin());
+    &(&x[0].max(&x[1]) * &hinge) + &(&picked + &x[2].clamp(0.0, 1.0))
+}
+
+#[test]
+fn replays_decide_branches_again() {
+    let mut plan = Plan::trace(branching, &[2.0, 1.0, 0.9]);
+
+    // The first two flip branches taken while tracing, the last takes them
+    // again.
+    for inputs in [[1.0, 3.0, 0.2], [-1.0, -2.0, 1.7], [2.0, 1.0, 0.9]] {
+        let output = plan.forward(&inputs);
+        let grads = plan.backward();
+
+        let leaves: Vec<Value> = inputs.iter().map(|&x| Value::new(x)).collect();
+        let fresh = branching(&leaves);
+        fresh.backward();
+        assert_eq!(output, fresh.value(), "at {:?}", inputs);
+        assert_eq!(grads, leaves.iter().map(Value::grad).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn softmax_shifts_by_the_current_maximum() {
+    let softmax = |x: &[Value]| Activation::Softmax.apply(x)[0];
+    let mut plan = Plan::trace(softmax, &[0.0, 5.0]);
+
+    // Shifting by the traced maximum would compute e^1000 here.
+    let output = plan.forward(&[1000.0, 0.0]);
+    assert_eq!(output, 1.0);
+    assert!(plan.backward().iter().all(|grad| grad.is_finite()));
+}
@@ -0,0 +1,53 @@
+use grad::tape::Scope;
+use grad::{Activation, Model, Value};
+
+// Literals have to keep working without annotations, as they did before
+// values became generic over their precision.
+#[test]
+fn literals_default_to_f64() {
+    let x = Value::new(2.0);
+    let y = (1.0 + &x).ln();
+    y.backward();
+
+    assert_eq!(y.value(), 3.0f64.ln());
+    assert_eq!(x.grad(), 1.0 / 3.0);
+}
+
+#[test]
+fn f32_values_use_their_own_tape() {
+    let _scope = Scope::<f32>::default();
+    let x = Value::<f32>::leaf(3.0);
+    let y = &(&x * &x) + 1.0;
+    y.backward();
+
+    assert_eq!(y.value(), 10.0f32);
+    assert_eq!(x.grad(), 6.0f32);
+    assert_eq!(Value::new(1.0).value(), 1.0f64);
+}
+
+#[test]
+fn models_convert_between_precisions() {
+    let model: Model = Model::new(&[3, 4, 2], &[Activation::Sigmoid, Activation::Softmax]);
+    let path = std::env::temp_dir().join(format!("grad-precision-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    model.save(path).unwrap();
+    let loaded = Model::<f32>::load(path);
+    std::fs::remove_file(path).unwrap();
+    let loaded = loaded.unwrap();
+
+    let cast = model.cast::<f32>();
+    let round_trip = cast.cast::<f64>();
+    for input in [[0.5, -1.0, 2.0], [0.0, 0.25, -0.75]] {
+        let expected = model.predict(&input);
+        let single = input.map(|x| x as f32);
+        assert_eq!(loaded.predict(&single), cast.predict(&single));
+        for ((&expected, single), round_trip) in expected
+            .iter()
+            .zip(cast.predict(&single))
+            .zip(round_trip.predict(&input))
+        {
+            assert!((f64::from(single) - expected).abs() < 1e-6);
+            assert!((round_trip - expected).abs() < 1e-6);
+        }
+    }
+}
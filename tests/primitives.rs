@@ -0,0 +1,81 @@
+use grad::Value;
+use grad::tape::{self, Scope};
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() <= 1e-12 * expected.abs().max(1.0),
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+/// Value of `f` at `x` and its derivative from `backward`.
+fn forward_and_backward(x: f64, f: impl Fn(&Value) -> Value) -> (f64, f64) {
+    let _scope = Scope::new();
+    let x = Value::new(x);
+    let y = f(&x);
+    y.backward();
+    (y.value(), x.grad())
+}
+
+#[test]
+fn exp_is_its_own_derivative() {
+    for x in [-3.0, 0.0, 0.5, 4.0] {
+        let (value, grad) = forward_and_backward(x, Value::exp);
+        assert_close(value, x.exp());
+        assert_close(grad, x.exp());
+    }
+}
+
+#[test]
+fn tanh_derivative_is_one_minus_square() {
+    for x in [-2.0, -0.1, 0.0, 1.3] {
+        let (value, grad) = forward_and_backward(x, Value::tanh);
+        assert_close(value, x.tanh());
+        assert_close(grad, 1.0 - x.tanh() * x.tanh());
+    }
+}
+
+#[test]
+fn sqrt_derivative_is_half_the_reciprocal() {
+    for x in [0.25, 1.0, 9.0] {
+        let (value, grad) = forward_and_backward(x, Value::sqrt);
+        assert_close(value, x.sqrt());
+        assert_close(grad, 0.5 / x.sqrt());
+    }
+}
+
+#[test]
+fn sigmoid_matches_its_closed_form() {
+    for x in [-5.0f64, 0.0, 2.5] {
+        let s = 1.0 / (1.0 + (-x).exp());
+        let (value, grad) = forward_and_backward(x, Value::sigmoid);
+        assert_close(value, s);
+        assert_close(grad, s * (1.0 - s));
+    }
+}
+
+#[test]
+fn sigmoid_saturates_without_overflowing() {
+    // exp(800) overflows, a naive 1 / (1 + e^-x) would give NaN gradients.
+    let (value, grad) = forward_and_backward(-800.0, Value::sigmoid);
+    assert_eq!(value, 0.0);
+    assert_eq!(grad, 0.0);
+
+    let (value, grad) = forward_and_backward(800.0, Value::sigmoid);
+    assert_eq!(value, 1.0);
+    assert_eq!(grad, 0.0);
+}
+
+#[test]
+fn each_primitive_is_a_single_node() {
+    let x = Value::new(0.7);
+    let primitives: [fn(&Value) -> Value; 4] =
+        [Value::exp, Value::tanh, Value::sqrt, Value::sigmoid];
+    for primitive in primitives {
+        let before = tape::len::<f64>();
+        primitive(&x);
+        assert_eq!(tape::len::<f64>(), before + 1);
+    }
+}
@@ -0,0 +1,88 @@
+use grad::Value;
+use grad::tape;
+
+fn leaves(values: &[f64]) -> Vec<Value> {
+    values.iter().map(|&x| Value::new(x)).collect()
+}
+
+#[test]
+fn sum_passes_the_gradient_to_every_term() {
+    let xs = leaves(&[1.0, -2.0, 3.5, 0.25]);
+    let total = Value::sum(&xs);
+    total.backward_with(2.0);
+
+    assert_eq!(total.value(), 2.75);
+    assert!(xs.iter().all(|x| x.grad() == 2.0));
+}
+
+#[test]
+fn repeated_terms_accumulate() {
+    let x = Value::new(3.0);
+    let y = Value::new(1.0);
+    let total = Value::sum(&[x, y, x, x]);
+    total.backward();
+
+    assert_eq!(total.value(), 10.0);
+    assert_eq!(x.grad(), 3.0);
+    assert_eq!(y.grad(), 1.0);
+}
+
+#[test]
+fn sum_of_nothing_is_zero() {
+    assert_eq!(Value::<f64>::sum(&[]).value(), 0.0);
+}
+
+#[test]
+fn mean_divides_the_gradient_evenly() {
+    let xs = leaves(&[2.0, 4.0, 9.0, 1.0]);
+    let mean = Value::mean(&xs);
+    mean.backward();
+
+    assert_eq!(mean.value(), 4.0);
+    assert!(xs.iter().all(|x| x.grad() == 0.25));
+}
+
+#[test]
+#[should_panic(expected = "Mean of no values")]
+fn mean_of_nothing_panics() {
+    Value::<f64>::mean(&[]);
+}
+
+#[test]
+fn dot_differentiates_each_side_by_the_other() {
+    let a = leaves(&[1.0, 2.0, 3.0]);
+    let b = leaves(&[-4.0, 0.5, 2.0]);
+    let dot = Value::dot(&a, &b);
+    dot.backward();
+
+    assert_eq!(dot.value(), -4.0 + 1.0 + 6.0);
+    let a_grads: Vec<f64> = a.iter().map(Value::grad).collect();
+    let b_grads: Vec<f64> = b.iter().map(Value::grad).collect();
+    assert_eq!(a_grads, [-4.0, 0.5, 2.0]);
+    assert_eq!(b_grads, [1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn dot_with_itself_doubles_the_gradient() {
+    let a = leaves(&[1.5, -2.0]);
+    Value::dot(&a, &a).backward();
+    assert_eq!(a[0].grad(), 3.0);
+    assert_eq!(a[1].grad(), -4.0);
+}
+
+#[test]
+#[should_panic(expected = "Dot product of different lengths")]
+fn dot_of_different_lengths_panics() {
+    Value::dot(&leaves(&[1.0, 2.0]), &leaves(&[1.0]));
+}
+
+#[test]
+fn reductions_record_a_single_node() {
+    let xs = leaves(&vec![0.5; 784]);
+    let weights = leaves(&vec![0.1; 784]);
+    let start = tape::len::<f64>();
+    Value::sum(&xs);
+    Value::mean(&xs);
+    Value::dot(&xs, &weights);
+    assert_eq!(tape::len::<f64>(), start + 3);
+}
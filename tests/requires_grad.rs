@@ -0,0 +1,106 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use grad::Value;
+use grad::autograd::grad;
+
+/// Registers a hook on `value` counting how often backward reaches it.
+fn visits(value: &Value) -> Rc<Cell<usize>> {
+    let count = Rc::new(Cell::new(0));
+    let counter = count.clone();
+    value.register_hook(move |grad| {
+        counter.set(counter.get() + 1);
+        grad
+    });
+    count
+}
+
+#[test]
+fn detached_branches_get_no_gradient_and_are_not_visited() {
+    let x = Value::new(3.0);
+    let hidden = &x * &x;
+    let hidden_visits = visits(&hidden);
+
+    let y = &(&x * 2.0) + &hidden.detach();
+    y.backward();
+
+    assert_eq!(y.value(), 15.0);
+    assert_eq!(x.grad(), 2.0);
+    assert_eq!(hidden.grad(), 0.0);
+    assert_eq!(hidden_visits.get(), 0);
+}
+
+#[test]
+fn detach_stops_the_gradient_of_a_shared_value_on_one_path_only() {
+    let x = Value::new(3.0);
+    let y = &x * &x.detach();
+    y.backward();
+    assert_eq!(x.grad(), 3.0);
+}
+
+#[test]
+fn untracked_leaves_and_constants_do_not_require_grad() {
+    let weight = Value::new(0.5);
+    let input = Value::untracked(4.0);
+    let target = Value::constant(1.0);
+    assert!(weight.requires_grad());
+    assert!(!input.requires_grad());
+    assert!(!target.requires_grad());
+
+    let data_only = &input - &target;
+    assert!(!data_only.requires_grad());
+    let error = &(&weight * &input) - &target;
+    assert!(error.requires_grad());
+
+    (&error * &error).backward();
+    assert_eq!(weight.grad(), 2.0 * 1.0 * 4.0);
+    assert_eq!(input.grad(), 0.0);
+    assert_eq!(target.grad(), 0.0);
+}
+
+#[test]
+fn subgraphs_without_trainable_inputs_are_not_visited() {
+    let input = Value::untracked(2.0);
+    let features = input.exp();
+    let weight = Value::new(1.5);
+    let y = &weight * &features;
+    y.backward();
+
+    assert!(!features.requires_grad());
+    assert_eq!(features.grad(), 0.0);
+    assert_eq!(weight.grad(), 2.0f64.exp());
+}
+
+#[test]
+fn turning_off_requires_grad_freezes_a_leaf() {
+    let frozen = Value::new(2.0);
+    let trained = Value::new(5.0);
+    frozen.set_requires_grad(false);
+
+    (&frozen * &trained).backward();
+    assert_eq!(frozen.grad(), 0.0);
+    assert_eq!(trained.grad(), 2.0);
+}
+
+#[test]
+#[should_panic(expected = "requires_grad can only be set on leaves")]
+fn requires_grad_cannot_be_changed_on_computed_values() {
+    let x = Value::new(1.0);
+    (&x + 1.0).set_requires_grad(false);
+}
+
+#[test]
+fn only_backward_runs_hooks() {
+    let x = Value::new(2.0);
+    let y = &x * &x;
+    y.register_hook(|grad| grad * 10.0);
+    let hook_visits = visits(&y);
+
+    assert_eq!(grad(&[y], &[x], false)[0].value(), 4.0);
+    assert_eq!(grad(&[y], &[x], true)[0].value(), 4.0);
+    assert_eq!(hook_visits.get(), 0);
+
+    y.backward();
+    assert_eq!(x.grad(), 40.0);
+    assert_eq!(hook_visits.get(), 1);
+}
@@ -0,0 +1,37 @@
+use grad::{Value, tape};
+
+#[test]
+#[should_panic(expected = "Value used after the tape was truncated")]
+fn value_from_dropped_scope_panics() {
+    let stale = {
+        let _scope = tape::Scope::new();
+        Value::new(42.0)
+    };
+    Value::new(7.0);
+    stale.value();
+}
+
+#[test]
+#[should_panic(expected = "Value used after the tape was truncated")]
+fn stale_value_cannot_be_used_as_operand() {
+    let x = Value::new(1.0);
+    let stale = {
+        let _scope = tape::Scope::new();
+        &x * 2.0
+    };
+    let _ = &x + &stale;
+}
+
+#[test]
+fn values_below_the_truncation_stay_valid() {
+    let x = Value::new(3.0);
+    {
+        let _scope = tape::Scope::new();
+        (&x * &x).backward();
+    }
+    x.zero_grad();
+    let y = &x * 2.0;
+    y.backward();
+    assert_eq!(x.value(), 3.0);
+    assert_eq!(x.grad(), 2.0);
+}
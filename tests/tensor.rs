@@ -0,0 +1,131 @@
+use grad::Tensor;
+
+fn cube() -> Tensor {
+    let values: Vec<f64> = (0..24).map(f64::from).collect();
+    Tensor::constant(&values, &[2, 3, 4])
+}
+
+#[test]
+fn permute_moves_axes() {
+    let permuted = cube().permute(&[2, 0, 1]);
+    assert_eq!(permuted.shape(), &[4, 2, 3]);
+    // Element [k][i][j] of the result is element [i][j][k] of the input.
+    assert_eq!(
+        permuted.get(&[3, 1, 2]).value(),
+        cube().get(&[1, 2, 3]).value()
+    );
+}
+
+#[test]
+#[should_panic(expected = "[0, 0, 1] is not a permutation of the axes 0..3")]
+fn permute_rejects_repeated_axes() {
+    cube().permute(&[0, 0, 1]);
+}
+
+#[test]
+#[should_panic(expected = "[0, 3, 1] is not a permutation of the axes 0..3")]
+fn permute_rejects_axes_out_of_range() {
+    cube().permute(&[0, 3, 1]);
+}
+
+#[test]
+fn zip_with_broadcasts_across_ranks() {
+    // [2, 3] against [3] and against [2, 1].
+    let a = Tensor::from_f64(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+    let row = Tensor::from_f64(&[10.0, 20.0, 30.0], &[3]);
+    let column = Tensor::from_f64(&[2.0, 3.0], &[2, 1]);
+
+    let sum = &a + &row;
+    assert_eq!(sum.shape(), &[2, 3]);
+    assert_eq!(sum.values(), vec![11.0, 22.0, 33.0, 14.0, 25.0, 36.0]);
+
+    let product = a.zip_with(&column, |a, b| a * b);
+    assert_eq!(product.shape(), &[2, 3]);
+    assert_eq!(product.values(), vec![2.0, 4.0, 6.0, 12.0, 15.0, 18.0]);
+
+    (&sum.sum() + &product.sum()).backward();
+    // Stretched operands collect the gradient of every element they fed.
+    assert_eq!(row.grads(), vec![2.0, 2.0, 2.0]);
+    assert_eq!(column.grads(), vec![6.0, 15.0]);
+    assert_eq!(a.grads(), vec![3.0, 3.0, 3.0, 4.0, 4.0, 4.0]);
+}
+
+#[test]
+fn zip_with_stretches_both_operands() {
+    let column = Tensor::from_f64(&[1.0, 2.0], &[2, 1]);
+    let row = Tensor::from_f64(&[10.0, 20.0, 30.0], &[1, 3]);
+    let outer = column.zip_with(&row, |a, b| a * b);
+    assert_eq!(outer.shape(), &[2, 3]);
+    assert_eq!(outer.values(), vec![10.0, 20.0, 30.0, 20.0, 40.0, 60.0]);
+
+    outer.sum().backward();
+    assert_eq!(column.grads(), vec![60.0, 60.0]);
+    assert_eq!(row.grads(), vec![3.0, 3.0, 3.0]);
+}
+
+#[test]
+#[should_panic(expected = "Shapes [2, 3] and [2] cannot be broadcast")]
+fn zip_with_rejects_incompatible_shapes() {
+    let a = Tensor::zeros(&[2, 3]);
+    let _ = &a + &Tensor::zeros(&[2]);
+}
+
+#[test]
+fn matmul_multiplies_matrices() {
+    let a = Tensor::from_f64(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+    let b = Tensor::from_f64(&[7.0, 8.0, 9.0, 10.0, 11.0, 12.0], &[3, 2]);
+    let product = a.matmul(&b);
+    assert_eq!(product.shape(), &[2, 2]);
+    assert_eq!(product.values(), vec![58.0, 64.0, 139.0, 154.0]);
+
+    // d(sum AB)/dA is the row sums of B for every row of A, and d/dB the
+    // column sums of A for every column of B.
+    product.sum().backward();
+    assert_eq!(a.grads(), vec![15.0, 19.0, 23.0, 15.0, 19.0, 23.0]);
+    assert_eq!(b.grads(), vec![5.0, 5.0, 7.0, 7.0, 9.0, 9.0]);
+}
+
+#[test]
+#[should_panic(expected = "Inner dimensions do not match")]
+fn matmul_checks_inner_dimensions() {
+    Tensor::zeros(&[2, 3]).matmul(&Tensor::zeros(&[2, 3]));
+}
+
+#[test]
+fn sum_and_mean_remove_their_axis() {
+    let sums = cube().sum_axis(1);
+    assert_eq!(sums.shape(), &[2, 4]);
+    assert_eq!(
+        sums.values(),
+        vec![12.0, 15.0, 18.0, 21.0, 48.0, 51.0, 54.0, 57.0]
+    );
+
+    let means = cube().mean_axis(2);
+    assert_eq!(means.shape(), &[2, 3]);
+    assert_eq!(means.values(), vec![1.5, 5.5, 9.5, 13.5, 17.5, 21.5]);
+}
+
+#[test]
+fn sum_and_mean_axis_spread_the_gradient() {
+    let values: Vec<f64> = (0..6).map(f64::from).collect();
+    let x = Tensor::from_f64(&values, &[2, 3]);
+    let weights = Tensor::constant(&[1.0, 2.0, 3.0], &[3]);
+    let weights_per_row = Tensor::constant(&[1.0, 2.0], &[2]);
+
+    let sums = x.sum_axis(0);
+    let means = x.mean_axis(1);
+    (&(&sums * &weights).sum() + &(&means * &weights_per_row).sum()).backward();
+
+    // Every element gets its column weight, plus a third of its row weight.
+    let expected = [
+        1.0 + 1.0 / 3.0,
+        2.0 + 1.0 / 3.0,
+        3.0 + 1.0 / 3.0,
+        1.0 + 2.0 / 3.0,
+        2.0 + 2.0 / 3.0,
+        3.0 + 2.0 / 3.0,
+    ];
+    for (grad, expected) in x.grads().iter().zip(expected) {
+        assert!((grad - expected).abs() < 1e-12, "{} vs {}", grad, expected);
+    }
+}
@@ -0,0 +1,65 @@
+use grad::Value;
+use grad::gradcheck::gradcheck;
+
+const EPSILON: f64 = 1e-6;
+const TOLERANCE: f64 = 1e-7;
+
+type Unary = fn(&Value) -> Value;
+
+fn check(name: &str, inputs: &[f64], f: impl Fn(&[Value]) -> Value) {
+    let report = gradcheck(f, inputs, EPSILON, TOLERANCE);
+    assert!(report.passed(), "{} at {:?}\n{}", name, inputs, report);
+}
+
+#[test]
+fn unary_functions_match_finite_differences() {
+    let functions: [(&str, Unary); 6] = [
+        ("sin", Value::sin),
+        ("cos", Value::cos),
+        ("tan", Value::tan),
+        ("sinh", Value::sinh),
+        ("cosh", Value::cosh),
+        ("ln", Value::ln),
+    ];
+    for (name, f) in functions {
+        for x in [0.1, 0.9, 1.4, 2.7] {
+            check(name, &[x], |x| f(&x[0]));
+        }
+    }
+}
+
+#[test]
+fn atan2_matches_finite_differences_in_every_quadrant() {
+    for (y, x) in [
+        (1.0, 2.0),
+        (1.0, -2.0),
+        (-1.5, -0.5),
+        (-1.5, 0.5),
+        (3.0, 0.0),
+    ] {
+        check("atan2", &[y, x], |v| v[0].atan2(&v[1]));
+    }
+}
+
+#[test]
+fn compositions_match_finite_differences() {
+    check("pendulum energy", &[0.8, 1.3], |v| {
+        let (theta, omega) = (&v[0], &v[1]);
+        &(&(omega * omega) * 0.5) + &(1.0 - &theta.cos())
+    });
+    check("sin(ln(x)) * cosh(y)", &[2.0, -0.4], |v| {
+        &v[0].ln().sin() * &v[1].cosh()
+    });
+}
+
+#[test]
+fn values_match_the_std_functions() {
+    let x = Value::new(0.6);
+    let y = Value::new(-1.1);
+    assert_eq!(x.sin().value(), 0.6f64.sin());
+    assert_eq!(x.cos().value(), 0.6f64.cos());
+    assert_eq!(x.tan().value(), 0.6f64.tan());
+    assert_eq!(x.sinh().value(), 0.6f64.sinh());
+    assert_eq!(x.cosh().value(), 0.6f64.cosh());
+    assert_eq!(y.atan2(&x).value(), (-1.1f64).atan2(0.6));
+}